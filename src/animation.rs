@@ -0,0 +1,251 @@
+//! Sprite animations, read from `/animations.ron`.
+//!
+//! The file has named sets of clips, one set per kind of character.
+//! A clip is a list of frames from the character's sprite sheet,
+//! each shown for its own length of time, and maybe with an event
+//! (like `"footstep"`) that fires when it comes up.  Entities play
+//! clips with an `Animation` component, which says which set it's
+//! using; `systems::Animate` moves them along every update.
+
+use std::collections::BTreeMap;
+
+use ggez::Context;
+use serde::Deserialize;
+
+use crate::components::Animation;
+use crate::resources::{self, Key, Loaded, Storage};
+use crate::types::Error;
+
+pub const ANIMATIONS_PATH: &str = "/animations.ron";
+
+/// What happens after the last frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum Mode {
+    /// Back to the first frame.
+    Loop,
+    /// Stays on the last frame.
+    Once,
+    /// Backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Frame {
+    /// Index into the sprite sheet, left to right then top to
+    /// bottom.
+    pub sheet_frame: usize,
+    /// Seconds.
+    pub duration: f32,
+    /// Fires when this frame comes up.
+    #[serde(default)]
+    pub event: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Clip {
+    pub mode: Mode,
+    pub frames: Vec<Frame>,
+}
+
+/// Frames shorter than this are shown for this long, so a zero in
+/// the file can't hang the game.
+const MIN_FRAME_DURATION: f32 = 0.001;
+
+impl Clip {
+    /// Moves `anim` along by `dt` seconds, calling `on_event` with
+    /// the event of each frame it comes to.
+    pub fn advance<F>(&self, anim: &mut Animation, dt: f32, mut on_event: F)
+    where
+        F: FnMut(&str),
+    {
+        if self.frames.is_empty() {
+            return;
+        }
+        if anim.frame >= self.frames.len() {
+            anim.restart();
+        }
+        if !anim.started {
+            anim.started = true;
+            self.enter_frame(anim, &mut on_event);
+        }
+        if anim.finished {
+            return;
+        }
+
+        anim.elapsed += dt;
+        loop {
+            let duration = self.frames[anim.frame].duration.max(MIN_FRAME_DURATION);
+            if anim.elapsed < duration {
+                break;
+            }
+            anim.elapsed -= duration;
+            match self.next_frame(anim) {
+                Some(next) => {
+                    anim.frame = next;
+                    self.enter_frame(anim, &mut on_event);
+                }
+                None => {
+                    anim.finished = true;
+                    anim.elapsed = 0.0;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn enter_frame<F>(&self, anim: &mut Animation, on_event: &mut F)
+    where
+        F: FnMut(&str),
+    {
+        let frame = &self.frames[anim.frame];
+        anim.sheet_frame = frame.sheet_frame;
+        if let Some(event) = frame.event.as_ref() {
+            on_event(event);
+        }
+    }
+
+    /// The frame after the current one, or `None` if a `Once` clip
+    /// is over.
+    fn next_frame(&self, anim: &mut Animation) -> Option<usize> {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            Mode::Loop => Some(if anim.frame >= last { 0 } else { anim.frame + 1 }),
+            Mode::Once => {
+                if anim.frame >= last {
+                    None
+                } else {
+                    Some(anim.frame + 1)
+                }
+            }
+            Mode::PingPong => {
+                if last == 0 {
+                    return Some(0);
+                }
+                if anim.backwards && anim.frame == 0 {
+                    anim.backwards = false;
+                } else if !anim.backwards && anim.frame >= last {
+                    anim.backwards = true;
+                }
+                Some(if anim.backwards { anim.frame - 1 } else { anim.frame + 1 })
+            }
+        }
+    }
+}
+
+/// Clips by name.
+pub type ClipSet = BTreeMap<String, Clip>;
+
+/// Every set of clips, by name.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AnimationSets(BTreeMap<String, ClipSet>);
+
+impl AnimationSets {
+    pub fn clip(&self, set: &str, clip: &str) -> Option<&Clip> {
+        self.0.get(set)?.get(clip)
+    }
+
+    pub fn has_clip(&self, set: &str, clip: &str) -> bool {
+        self.clip(set, clip).is_some()
+    }
+}
+
+impl warmy::Load<Context, Key> for AnimationSets {
+    type Error = Error;
+    fn load(key: Key, _storage: &mut Storage, ctx: &mut Context) -> Result<Loaded<Self>, Self::Error> {
+        resources::load_ron_resource(key, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clip of `n` quarter-second frames, showing sheet frames 10,
+    /// 11... and firing events "0", "1"...
+    fn clip(mode: Mode, n: usize) -> Clip {
+        let frames = (0..n)
+            .map(|i| Frame {
+                sheet_frame: 10 + i,
+                duration: 0.25,
+                event: Some(i.to_string()),
+            })
+            .collect();
+        Clip { mode, frames }
+    }
+
+    /// Advances by `dt` and returns the events that fired.
+    fn advance(clip: &Clip, anim: &mut Animation, dt: f32) -> Vec<String> {
+        let mut events = Vec::new();
+        clip.advance(anim, dt, |e| events.push(e.to_owned()));
+        events
+    }
+
+    /// The frames a clip shows, a quarter second apart.
+    fn frames(clip: &Clip, count: usize) -> Vec<usize> {
+        let mut anim = Animation::new("grandpa", "walk");
+        advance(clip, &mut anim, 0.0);
+        let mut frames = vec![anim.frame];
+        for _ in 1..count {
+            advance(clip, &mut anim, 0.25);
+            frames.push(anim.frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn the_first_frame_fires_as_soon_as_it_starts() {
+        let clip = clip(Mode::Loop, 3);
+        let mut anim = Animation::new("grandpa", "walk");
+        assert_eq!(advance(&clip, &mut anim, 0.1), vec!["0"]);
+        assert_eq!(anim.sheet_frame, 10);
+        assert!(advance(&clip, &mut anim, 0.1).is_empty());
+        assert_eq!(advance(&clip, &mut anim, 0.1), vec!["1"]);
+        assert_eq!(anim.sheet_frame, 11);
+    }
+
+    #[test]
+    fn loops_go_back_to_the_start() {
+        assert_eq!(frames(&clip(Mode::Loop, 3), 7), vec![0, 1, 2, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let clip = clip(Mode::Once, 3);
+        assert_eq!(frames(&clip, 6), vec![0, 1, 2, 2, 2, 2]);
+
+        let mut anim = Animation::new("grandpa", "walk");
+        advance(&clip, &mut anim, 10.0);
+        assert!(anim.finished);
+        assert_eq!(anim.sheet_frame, 12);
+        assert!(advance(&clip, &mut anim, 10.0).is_empty());
+    }
+
+    #[test]
+    fn ping_pong_turns_around_at_both_ends() {
+        assert_eq!(frames(&clip(Mode::PingPong, 3), 9), vec![0, 1, 2, 1, 0, 1, 2, 1, 0]);
+        assert_eq!(frames(&clip(Mode::PingPong, 1), 3), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn a_long_step_catches_up_on_every_frame() {
+        let clip = clip(Mode::Loop, 3);
+        let mut anim = Animation::new("grandpa", "walk");
+        advance(&clip, &mut anim, 0.0);
+        assert_eq!(advance(&clip, &mut anim, 1.1), vec!["1", "2", "0", "1"]);
+        assert_eq!(anim.frame, 1);
+        assert!((anim.elapsed - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn zero_length_frames_dont_hang() {
+        let mut clip = clip(Mode::Loop, 2);
+        for frame in &mut clip.frames {
+            frame.duration = 0.0;
+        }
+        let mut anim = Animation::new("grandpa", "walk");
+        let events = advance(&clip, &mut anim, 0.01);
+        // The first frame, then one for every millisecond.
+        assert!((10..=12).contains(&events.len()), "{} events", events.len());
+    }
+}
@@ -0,0 +1,115 @@
+//! Command-line flags, for scripting launches and jumping straight
+//! to whatever's being worked on.
+
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(name = "grandpas-big-adventure")]
+pub struct Args {
+    /// Directory to load game data from, instead of `resources`.
+    #[structopt(long, parse(from_os_str))]
+    pub resources: Option<PathBuf>,
+
+    /// Scene to start in, by name, like `LevelScene`.
+    #[structopt(long)]
+    pub scene: Option<String>,
+
+    /// Window width, instead of the one in the settings.
+    #[structopt(long)]
+    pub width: Option<u32>,
+
+    /// Window height, instead of the one in the settings.
+    #[structopt(long)]
+    pub height: Option<u32>,
+
+    /// Start fullscreen, whatever the settings say.
+    #[structopt(long, conflicts_with = "windowed")]
+    pub fullscreen: bool,
+
+    /// Start in a window, whatever the settings say.
+    #[structopt(long)]
+    pub windowed: bool,
+
+    /// What to log: a level (off, error, warn, info, debug or
+    /// trace), optionally followed by `module=level` overrides, like
+    /// `warn,grandpas_big_adventure::scenes=trace`.
+    #[structopt(long)]
+    pub log_level: Option<crate::logging::Filter>,
+
+    /// Save slot to load, numbered from 1 like on the load screen.
+    /// Starts in the level unless `--scene` says otherwise.
+    #[structopt(long, parse(try_from_str = parse_slot))]
+    pub load: Option<usize>,
+
+    /// Records input to this file, for `--replay`.
+    #[structopt(long, parse(from_os_str), conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Plays back input recorded with `--record` instead of reading
+    /// the keyboard.
+    #[structopt(long, parse(from_os_str))]
+    pub replay: Option<PathBuf>,
+
+    /// Seed for the random number generator.  Random if not given,
+    /// or the recorded one when replaying.
+    #[structopt(long)]
+    pub seed: Option<u64>,
+
+    /// Draws the starting scene to this PNG file and quits, without
+    /// running the game.
+    #[structopt(long, parse(from_os_str))]
+    pub render_to: Option<PathBuf>,
+}
+
+/// A save slot number as the player sees it, from 1 up.
+fn parse_slot(s: &str) -> Result<usize, String> {
+    let slot: usize = s.parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+    if (1..=crate::save::SLOT_COUNT).contains(&slot) {
+        Ok(slot)
+    } else {
+        Err(format!("there are slots 1 to {}", crate::save::SLOT_COUNT))
+    }
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        Self::from_args()
+    }
+
+    /// The window size and fullscreen-ness asked for, filling in
+    /// whatever wasn't from `settings`.
+    pub fn window_mode(&self, settings: &crate::settings::Settings) -> ggez::conf::WindowMode {
+        let (w, h) = settings.resolution;
+        let (w, h) = (self.width.unwrap_or(w), self.height.unwrap_or(h));
+        let fullscreen = (settings.fullscreen || self.fullscreen) && !self.windowed;
+        let fullscreen_type = if fullscreen {
+            ggez::conf::FullscreenType::Desktop
+        } else {
+            ggez::conf::FullscreenType::Windowed
+        };
+        settings
+            .window_mode()
+            .dimensions(w as f32, h as f32)
+            .fullscreen_type(fullscreen_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(flags: &[&str]) -> Result<Args, structopt::clap::Error> {
+        Args::from_iter_safe(std::iter::once("grandpas-big-adventure").chain(flags.iter().cloned()))
+    }
+
+    #[test]
+    fn load_takes_slots_numbered_from_one() {
+        assert_eq!(args(&["--load", "1"]).unwrap().load, Some(1));
+        assert_eq!(args(&["--load", "3"]).unwrap().load, Some(3));
+        assert!(args(&["--load", "0"]).is_err());
+        assert!(args(&["--load", "4"]).is_err());
+        assert!(args(&["--load", "first"]).is_err());
+    }
+}
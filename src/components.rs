@@ -0,0 +1,243 @@
+//! Components for the entities in the specs world.
+
+use serde::{Deserialize, Serialize};
+use specs::{self, Component, NullStorage, VecStorage};
+use specs_derive::Component;
+
+/// Where an entity is, in world pixels.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Position {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    /// Where to draw an entity `alpha` of the way from `previous`
+    /// to here.
+    pub fn lerp_from(&self, previous: &PreviousPosition, alpha: f32) -> Self {
+        Self {
+            x: previous.x + (self.x - previous.x) * alpha,
+            y: previous.y + (self.y - previous.y) * alpha,
+        }
+    }
+}
+
+/// Where an entity was before the latest update, so drawing can
+/// blend between updates.  `RememberPositions` keeps it up to date
+/// and adds it to anything with a `Position`.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Component)]
+#[storage(VecStorage)]
+pub struct PreviousPosition {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// How fast an entity is moving, in world pixels per second.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Component, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Velocity {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Which animation an entity is playing, and how far into it it is.
+/// See `animation` for where the clips come from.
+#[derive(Debug, Clone, Default, PartialEq, Component, Deserialize)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Animation {
+    /// Set of clips in `animations.ron` to play from.
+    pub set: String,
+    pub clip: String,
+    /// Switches between the `idle`, `walk` and `run` clips by how
+    /// fast the entity is moving.
+    pub by_speed: bool,
+    /// Index into the clip's frames.
+    #[serde(skip)]
+    pub frame: usize,
+    /// Seconds the current frame has been showing.
+    #[serde(skip)]
+    pub elapsed: f32,
+    /// Which way a ping-pong clip is going.
+    #[serde(skip)]
+    pub backwards: bool,
+    /// Whether the first frame has come up yet, to fire its event.
+    #[serde(skip)]
+    pub started: bool,
+    /// Whether a clip that plays once is over.
+    #[serde(skip)]
+    pub finished: bool,
+    /// The sprite sheet frame to draw.
+    #[serde(skip)]
+    pub sheet_frame: usize,
+}
+
+impl Animation {
+    #[cfg(test)]
+    pub fn new(set: &str, clip: &str) -> Self {
+        Self {
+            set: set.to_owned(),
+            clip: clip.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Switches to another clip from the start, unless it's already
+    /// the one playing.
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            self.clip = clip.to_owned();
+            self.restart();
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.backwards = false;
+        self.started = false;
+        self.finished = false;
+    }
+}
+
+/// How to draw an entity: a frame of a sprite sheet.  If it has an
+/// `Animation` too, that picks the frame.
+#[derive(Debug, Clone, PartialEq, Component, Deserialize)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Sprite {
+    /// Path of the sheet in the resources, like `"/images/grandpa.png"`.
+    pub image: String,
+    /// Size of each frame, in image pixels.  Zero means the whole
+    /// image is one frame.
+    pub frame_size: (f32, f32),
+    /// Frame to draw, left to right then top to bottom, when there's
+    /// no animation.
+    pub frame: usize,
+    /// Higher layers draw over lower ones; within a layer, whatever's
+    /// further down the screen draws on top.
+    pub layer: i32,
+    /// The point in the frame that sits on the entity's position,
+    /// from (0, 0) at the top left to (1, 1) at the bottom right.
+    pub origin: (f32, f32),
+}
+
+impl Default for Sprite {
+    fn default() -> Self {
+        Self {
+            image: String::new(),
+            frame_size: (0.0, 0.0),
+            frame: 0,
+            layer: 0,
+            // Standing on the spot.
+            origin: (0.5, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// Whichever way `(dx, dy)` mostly points.
+    pub fn of(dx: f32, dy: f32) -> Self {
+        if dx.abs() > dy.abs() {
+            if dx < 0.0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            }
+        } else if dy < 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        }
+    }
+}
+
+/// Which way an entity is looking.  Sprites face right on their
+/// sheets and get flipped to face left.
+#[derive(Debug, Copy, Clone, PartialEq, Component, Deserialize)]
+#[storage(VecStorage)]
+pub struct Facing(pub Direction);
+
+impl Default for Facing {
+    fn default() -> Self {
+        Facing(Direction::Down)
+    }
+}
+
+/// What an NPC does with itself.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub enum Behaviour {
+    /// Stands around.
+    #[default]
+    Idle,
+    /// Strolls to random spots within `radius` of where it was
+    /// spawned, stopping for a bit at each.
+    Wander { radius: f32 },
+    /// Walks from point to point, then starts over if `looping`, or
+    /// walks back the way it came if not.
+    FollowPath { points: Vec<(f32, f32)>, looping: bool },
+}
+
+/// A character the player can talk to.  `systems::NpcBehaviour`
+/// moves it around.
+#[derive(Debug, Clone, Default, PartialEq, Component, Deserialize)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Npc {
+    pub behaviour: Behaviour,
+    /// Walking speed, in pixels per second.
+    pub speed: f32,
+    /// When the player comes this close, it stops and looks at them.
+    /// Zero means it never notices.
+    pub notice_radius: f32,
+    /// String table key for its name.
+    pub name: String,
+    /// String table keys for what it says, in order.
+    pub dialogue: Vec<String>,
+    /// Where it was first seen, for wandering around.
+    #[serde(skip)]
+    pub home: Option<Position>,
+    /// Where it's walking to.
+    #[serde(skip)]
+    pub target: Option<Position>,
+    /// Seconds left to stand still before wandering on.
+    #[serde(skip)]
+    pub wait: f32,
+    /// Path point it's walking to.
+    #[serde(skip)]
+    pub path_index: usize,
+    /// Whether it's walking a non-looping path backwards.
+    #[serde(skip)]
+    pub path_backwards: bool,
+}
+
+/// Marks the entity the player controls.  There should only
+/// ever be one of these.
+#[derive(Debug, Copy, Clone, Default, Component, Deserialize)]
+#[storage(NullStorage)]
+pub struct Player;
+
+/// Registers every component type with a fresh specs world.
+pub fn register_components(specs_world: &mut specs::World) {
+    specs_world.register::<Position>();
+    specs_world.register::<PreviousPosition>();
+    specs_world.register::<Velocity>();
+    specs_world.register::<Animation>();
+    specs_world.register::<Sprite>();
+    specs_world.register::<Facing>();
+    specs_world.register::<Npc>();
+    specs_world.register::<Player>();
+}
@@ -0,0 +1,413 @@
+//! What the console can do.
+//!
+//! Each command is a name, some help text and a function that gets
+//! the world and the words typed after the name.  Commands that
+//! change scenes queue requests on the world like any scene would.
+
+use crate::components;
+use crate::prefab::Prefab;
+use crate::scenes;
+use crate::timestep;
+use crate::world::World;
+
+/// What a command can see besides the world.
+pub struct Env {
+    /// Names of the scenes on the stack, bottom first.
+    pub stack: Vec<String>,
+    /// Every scene that can be pushed by name.
+    pub registered: Vec<String>,
+}
+
+impl Env {
+    pub fn new(stack: &scenes::Stack) -> Self {
+        Self {
+            stack: stack.names().into_iter().map(str::to_owned).collect(),
+            registered: stack.registry().names().map(str::to_owned).collect(),
+        }
+    }
+}
+
+/// Lines to print, or what went wrong.
+pub type Output = Result<Vec<String>, String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    run: fn(&mut World, &Env, &[&str]) -> Output,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "help [command]",
+        help: "Lists commands, or explains one.",
+        run: help,
+    },
+    Command {
+        name: "clear",
+        usage: "clear",
+        help: "Empties the scrollback.",
+        // The console handles this one itself.
+        run: |_, _, _| Ok(Vec::new()),
+    },
+    Command {
+        name: "scene",
+        usage: "scene push|replace <name> | scene pop | scene list",
+        help: "Changes scenes by name, or lists them.",
+        run: scene,
+    },
+    Command {
+        name: "spawn",
+        usage: "spawn <prefab> <x> <y>",
+        help: "Makes an entity at a spot in the level.",
+        run: spawn,
+    },
+    Command {
+        name: "set",
+        usage: "set flag <name> <value>",
+        help: "Sets a story flag.",
+        run: set,
+    },
+    Command {
+        name: "give",
+        usage: "give <item>",
+        help: "Puts an item in the inventory.",
+        run: give,
+    },
+    Command {
+        name: "teleport",
+        usage: "teleport <x> <y>",
+        help: "Moves the player.",
+        run: teleport,
+    },
+    Command {
+        name: "time",
+        usage: "time scale <x> | time pause | time resume | time step [ticks]",
+        help: "Slows down, stops or steps game time.",
+        run: time,
+    },
+    Command {
+        name: "reload",
+        usage: "reload",
+        help: "Rebuilds the current scene from scratch, picking up changed files.",
+        run: reload,
+    },
+];
+
+fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// Runs one line of input.
+pub fn execute(world: &mut World, env: &Env, line: &str) -> Output {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.split_first() {
+        Some((name, args)) => match find(name) {
+            Some(command) => (command.run)(world, env, args),
+            None => Err(format!("No command {:?}; try `help`", name)),
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Things that could go where the last word of `line` is.
+pub fn complete(world: &World, env: &Env, line: &str) -> Vec<String> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    // A trailing space means we're starting a new word.
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        words.push("");
+    }
+    let (partial, before) = match words.split_last() {
+        Some((partial, before)) => (*partial, before),
+        None => return Vec::new(),
+    };
+
+    let options: Vec<String> = match before {
+        [] => COMMANDS.iter().map(|c| c.name.to_owned()).collect(),
+        ["help"] => COMMANDS.iter().map(|c| c.name.to_owned()).collect(),
+        ["scene"] => strings(&["push", "replace", "pop", "list"]),
+        ["scene", "push"] | ["scene", "replace"] => env.registered.clone(),
+        ["spawn"] => world.prefabs.borrow().names().map(str::to_owned).collect(),
+        ["set"] => strings(&["flag"]),
+        ["set", "flag"] => world.game_state.flags.keys().cloned().collect(),
+        ["time"] => strings(&["scale", "pause", "resume", "step"]),
+        _ => Vec::new(),
+    };
+    options.into_iter().filter(|o| o.starts_with(partial)).collect()
+}
+
+fn strings(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| (*w).to_owned()).collect()
+}
+
+fn usage(name: &str) -> String {
+    format!("Usage: {}", find(name).map_or("", |c| c.usage))
+}
+
+fn parse<T: std::str::FromStr>(name: &str, word: &str) -> Result<T, String> {
+    word.parse()
+        .map_err(|_| format!("{:?} isn't a number. {}", word, usage(name)))
+}
+
+fn help(_world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    match args {
+        [] => Ok(COMMANDS
+            .iter()
+            .map(|c| format!("{:<10} {}", c.name, c.help))
+            .collect()),
+        [name] => match find(name) {
+            Some(c) => Ok(vec![c.usage.to_owned(), c.help.to_owned()]),
+            None => Err(format!("No command {:?}", name)),
+        },
+        _ => Err(usage("help")),
+    }
+}
+
+fn scene(world: &mut World, env: &Env, args: &[&str]) -> Output {
+    let check = |name: &str| {
+        if env.registered.iter().any(|n| n == name) {
+            Ok(name.to_owned())
+        } else {
+            Err(format!("No scene {:?}; try `scene list`", name))
+        }
+    };
+    match args {
+        ["push", name] => {
+            world.request_scene(scenes::Request::Push(check(name)?));
+            Ok(vec![format!("Pushing {}", name)])
+        }
+        ["replace", name] => {
+            world.request_scene(scenes::Request::Replace(check(name)?));
+            Ok(vec![format!("Replacing with {}", name)])
+        }
+        ["pop"] => {
+            world.request_scene(scenes::Request::Pop);
+            Ok(vec!["Popping".to_owned()])
+        }
+        ["list"] => Ok(vec![
+            format!("On the stack: {}", env.stack.join(" > ")),
+            format!("Available: {}", env.registered.join(", ")),
+        ]),
+        _ => Err(usage("scene")),
+    }
+}
+
+fn spawn(world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    match args {
+        [prefab, x, y] => {
+            let (x, y) = (parse("spawn", x)?, parse("spawn", y)?);
+            match world.spawn(prefab, &Prefab::at(x, y)) {
+                Some(entity) => Ok(vec![format!("Spawned {} as {:?}", prefab, entity)]),
+                None => Err(format!(
+                    "No prefab {:?}; there's {}",
+                    prefab,
+                    world.prefabs.borrow().names().collect::<Vec<_>>().join(", ")
+                )),
+            }
+        }
+        _ => Err(usage("spawn")),
+    }
+}
+
+fn set(world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    match args {
+        ["flag", name, value] => {
+            let value = parse("set", value)?;
+            world.game_state.set_flag(name, value);
+            Ok(vec![format!("{} = {}", name, value)])
+        }
+        _ => Err(usage("set")),
+    }
+}
+
+fn give(world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    if args.is_empty() {
+        return Err(usage("give"));
+    }
+    let item = args.join(" ");
+    world.game_state.inventory.push(item.clone());
+    Ok(vec![format!("Gave {}", item)])
+}
+
+fn teleport(world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    match args {
+        [x, y] => {
+            let (x, y) = (parse("teleport", x)?, parse("teleport", y)?);
+            let player = world.player().ok_or_else(|| "There's no player".to_owned())?;
+            let mut positions = world.specs_world.write_storage::<components::Position>();
+            positions
+                .insert(player, components::Position::new(x, y))
+                .map_err(|e| e.to_string())?;
+            Ok(vec![format!("Teleported to ({}, {})", x, y)])
+        }
+        _ => Err(usage("teleport")),
+    }
+}
+
+fn time(world: &mut World, _env: &Env, args: &[&str]) -> Output {
+    let time = &mut world.time;
+    match args {
+        ["scale", scale] => {
+            let scale: f32 = parse("time", scale)?;
+            if !scale.is_finite() {
+                return Err(format!("Time can't run at {}x", scale));
+            }
+            if scale < 0.0 {
+                return Err("Time can't run backwards".to_owned());
+            }
+            let set = time.set_scale(scale);
+            if set < scale {
+                Ok(vec![format!("Game time at {}x, the fastest it goes", set)])
+            } else {
+                Ok(vec![format!("Game time at {}x", set)])
+            }
+        }
+        ["pause"] => {
+            time.paused = true;
+            Ok(vec!["Paused; `time step` runs a tick".to_owned()])
+        }
+        ["resume"] => {
+            time.paused = false;
+            Ok(vec!["Resumed".to_owned()])
+        }
+        ["step"] => {
+            time.step(1);
+            Ok(vec!["Stepped 1 tick".to_owned()])
+        }
+        ["step", ticks] => {
+            let ticks: u32 = parse("time", ticks)?;
+            let added = time.step(ticks);
+            if added < ticks {
+                Ok(vec![format!(
+                    "Stepped {} ticks, all that fit; at most {} can wait at once",
+                    added,
+                    timestep::TimeControl::MAX_STEPS
+                )])
+            } else {
+                Ok(vec![format!("Stepped {} ticks", ticks)])
+            }
+        }
+        _ => Err(usage("time")),
+    }
+}
+
+fn reload(world: &mut World, env: &Env, _args: &[&str]) -> Output {
+    match env.stack.last() {
+        // Scenes like `DialogueScene` only come from other scenes,
+        // and can't be built again by name.
+        Some(name) if !env.registered.contains(name) => {
+            Err(format!("{} can't be rebuilt by name", name))
+        }
+        Some(name) => {
+            world.request_scene(scenes::Request::Replace(name.clone()));
+            Ok(vec![format!("Reloading {}", name)])
+        }
+        None => Err("No scene to reload".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::settings::Settings;
+
+    fn world() -> World {
+        let resource_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+        World::headless(&resource_dir, Settings::default(), 0)
+    }
+
+    fn env(stack: &[&str]) -> Env {
+        Env {
+            stack: strings(stack),
+            registered: strings(&["LevelScene", "TitleScene"]),
+        }
+    }
+
+    #[test]
+    fn lines_run_the_command_they_start_with() {
+        let mut world = world();
+        let env = env(&["TitleScene"]);
+        assert_eq!(execute(&mut world, &env, ""), Ok(Vec::new()));
+        assert_eq!(execute(&mut world, &env, "   give  old   hat "), Ok(vec!["Gave old hat".to_owned()]));
+        assert_eq!(world.game_state.inventory, vec!["old hat"]);
+        let err = execute(&mut world, &env, "fly").unwrap_err();
+        assert!(err.starts_with("No command \"fly\""), "{}", err);
+    }
+
+    #[test]
+    fn help_lists_every_command_or_explains_one() {
+        let mut world = world();
+        let env = env(&[]);
+        let lines = execute(&mut world, &env, "help").unwrap();
+        assert_eq!(lines.len(), COMMANDS.len());
+        assert_eq!(
+            execute(&mut world, &env, "help give").unwrap(),
+            vec!["give <item>", "Puts an item in the inventory."]
+        );
+        assert!(execute(&mut world, &env, "help fly").is_err());
+    }
+
+    #[test]
+    fn bad_arguments_show_the_usage() {
+        let mut world = world();
+        let env = env(&[]);
+        let err = execute(&mut world, &env, "set flag met_cat lots").unwrap_err();
+        assert_eq!(err, "\"lots\" isn't a number. Usage: set flag <name> <value>");
+        assert_eq!(execute(&mut world, &env, "spawn kiwi 1").unwrap_err(), "Usage: spawn <prefab> <x> <y>");
+        assert_eq!(execute(&mut world, &env, "give").unwrap_err(), "Usage: give <item>");
+
+        assert!(execute(&mut world, &env, "set flag met_cat -2").is_ok());
+        assert_eq!(world.game_state.flags.get("met_cat"), Some(&-2));
+    }
+
+    #[test]
+    fn time_can_be_slowed_and_stepped() {
+        let mut world = world();
+        let env = env(&[]);
+        assert!(execute(&mut world, &env, "time scale -1").is_err());
+        assert!(execute(&mut world, &env, "time scale inf").is_err());
+        execute(&mut world, &env, "time scale 0.5").unwrap();
+        assert_eq!(world.time.scale, 0.5);
+
+        execute(&mut world, &env, "time step 3").unwrap();
+        assert!(world.time.paused);
+        let lines = execute(&mut world, &env, "time step 4000000000").unwrap();
+        assert!(lines[0].contains("all that fit"), "{:?}", lines);
+    }
+
+    #[test]
+    fn scenes_have_to_be_registered() {
+        let mut world = world();
+        let env = env(&["TitleScene"]);
+        assert!(execute(&mut world, &env, "scene push DialogueScene").is_err());
+        assert!(execute(&mut world, &env, "scene push LevelScene").is_ok());
+        assert_eq!(world.scene_requests, vec![scenes::Request::Push("LevelScene".to_owned())]);
+    }
+
+    #[test]
+    fn reload_only_rebuilds_registered_scenes() {
+        let mut world = world();
+        let err = execute(&mut world, &env(&["LevelScene", "DialogueScene"]), "reload").unwrap_err();
+        assert_eq!(err, "DialogueScene can't be rebuilt by name");
+        assert!(world.scene_requests.is_empty());
+
+        assert!(execute(&mut world, &env(&["LevelScene"]), "reload").is_ok());
+        assert_eq!(world.scene_requests, vec![scenes::Request::Replace("LevelScene".to_owned())]);
+        assert!(execute(&mut world, &env(&[]), "reload").is_err());
+    }
+
+    #[test]
+    fn completion_knows_each_words_options() {
+        let world = world();
+        let env = env(&[]);
+        assert_eq!(complete(&world, &env, "").len(), COMMANDS.len());
+        assert_eq!(complete(&world, &env, "sc"), vec!["scene"]);
+        assert_eq!(complete(&world, &env, "scene p"), vec!["push", "pop"]);
+        assert_eq!(complete(&world, &env, "scene push "), vec!["LevelScene", "TitleScene"]);
+        assert_eq!(complete(&world, &env, "time st"), vec!["step"]);
+        assert!(complete(&world, &env, "give h").is_empty());
+    }
+}
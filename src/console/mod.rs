@@ -0,0 +1,422 @@
+//! A drop-down console for poking at the game while it runs.
+//!
+//! The backquote key slides it down over whatever scene is showing.
+//! While it's open it gets all the keyboard input; the scenes keep
+//! updating underneath.  It shows recent log lines along with the
+//! output of the commands in `commands`.
+
+use std::collections::VecDeque;
+
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color, DrawParam, Rect};
+use ggez::{timer, Context, GameResult};
+
+use crate::input::KeyPress;
+use crate::types::Point2;
+use crate::logging;
+use crate::ui;
+use crate::world::World;
+
+pub mod commands;
+
+pub use self::commands::Env;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+
+/// Lines of output kept for scrolling back through.
+const SCROLLBACK_LEN: usize = 1000;
+/// Commands remembered for Up and Down.
+const HISTORY_LEN: usize = 100;
+/// Lines PageUp and PageDown move by.
+const PAGE_LINES: usize = 10;
+
+/// How much of the screen the console covers when open.
+const HEIGHT_FRACTION: f32 = 0.5;
+/// Seconds it takes to slide all the way open or shut.
+const SLIDE_SECONDS: f32 = 0.15;
+/// Line height, as a multiple of the text size.
+const LINE_SPACING: f32 = 1.25;
+const MARGIN: f32 = 8.0;
+/// Longest command line, in columns.
+const INPUT_MAX_LEN: usize = 200;
+
+const PROMPT: &str = "> ";
+
+const LOG_COLOR: Color = Color {
+    r: 0.6,
+    g: 0.6,
+    b: 0.6,
+    a: 1.0,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LineKind {
+    Log,
+    Command,
+    Output,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    kind: LineKind,
+    text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Console {
+    open: bool,
+    /// How far down it's slid, from 0 (hidden) to 1 (open).
+    slide: f32,
+    input: ui::TextField,
+    scrollback: VecDeque<Line>,
+    /// Lines scrolled back from the bottom.
+    scroll: usize,
+    history: Vec<String>,
+    /// Which history entry is in the input line, if we're going
+    /// through them, and what was typed before we started.
+    history_pos: Option<usize>,
+    draft: String,
+    /// Log lines already copied into the scrollback.
+    log_seen: u64,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            slide: 0.0,
+            input: ui::TextField::new(INPUT_MAX_LEN),
+            scrollback: VecDeque::new(),
+            scroll: 0,
+            history: Vec::new(),
+            history_pos: None,
+            draft: String::new(),
+            log_seen: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Slides the console and picks up new log lines.  Call once per
+    /// frame, open or not, so nothing logged while it's shut is
+    /// missed.
+    pub fn update(&mut self, ctx: &Context) {
+        let step = timer::duration_to_f64(timer::delta(ctx)) as f32 / SLIDE_SECONDS;
+        self.slide = if self.open {
+            (self.slide + step).min(1.0)
+        } else {
+            (self.slide - step).max(0.0)
+        };
+
+        let (lines, seen) = logging::recent_lines(self.log_seen);
+        self.log_seen = seen;
+        for line in lines {
+            self.print(LineKind::Log, line);
+        }
+    }
+
+    /// A typed character.
+    pub fn text_input(&mut self, c: char) {
+        // The toggle key types one of these as it opens the console.
+        if c.is_control() || c == '`' || c == '~' {
+            return;
+        }
+        self.input.insert_char(c);
+    }
+
+    /// A key press, which might run a command.  Returns the command
+    /// line if it did, for recordings.
+    pub fn key_down(&mut self, world: &mut World, env: &Env, key: KeyPress) -> Option<String> {
+        match key.keycode {
+            KeyCode::Return | KeyCode::NumpadEnter => return self.submit(world, env),
+            KeyCode::Tab => self.complete(world, env),
+            KeyCode::Up => self.history_back(),
+            KeyCode::Down => self.history_forward(),
+            KeyCode::PageUp => self.scroll += PAGE_LINES,
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(PAGE_LINES),
+            KeyCode::Escape => self.open = false,
+            _ => {
+                self.input.handle_key(key);
+            }
+        }
+        None
+    }
+
+    fn print(&mut self, kind: LineKind, text: String) {
+        for line in text.lines() {
+            if self.scrollback.len() == SCROLLBACK_LEN {
+                self.scrollback.pop_front();
+            }
+            self.scrollback.push_back(Line {
+                kind,
+                text: line.to_owned(),
+            });
+            // Keep the view where it was if scrolled back.
+            if self.scroll > 0 {
+                self.scroll += 1;
+            }
+        }
+    }
+
+    fn submit(&mut self, world: &mut World, env: &Env) -> Option<String> {
+        let line = self.input.text().trim().to_owned();
+        self.input.clear();
+        self.history_pos = None;
+        self.scroll = 0;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            if self.history.len() == HISTORY_LEN {
+                self.history.remove(0);
+            }
+            self.history.push(line.clone());
+        }
+
+        self.print(LineKind::Command, format!("{}{}", PROMPT, line));
+        if line == "clear" {
+            self.scrollback.clear();
+            return None;
+        }
+        match commands::execute(world, env, &line) {
+            Ok(output) => {
+                for text in output {
+                    self.print(LineKind::Output, text);
+                }
+            }
+            Err(e) => self.print(LineKind::Error, e),
+        }
+        Some(line)
+    }
+
+    /// Finishes the word being typed.  If there's more than one way
+    /// to, goes as far as they agree and lists them.
+    fn complete(&mut self, world: &World, env: &Env) {
+        let line = self.input.text().to_owned();
+        let options = commands::complete(world, env, &line);
+        let word_start = line.trim_end_matches(|c: char| !c.is_whitespace()).len();
+        let head = &line[..word_start];
+        match options.as_slice() {
+            [] => (),
+            [only] => self.input.set_text(&format!("{}{} ", head, only)),
+            _ => {
+                let common = options.iter().skip(1).fold(options[0].as_str(), |prefix, o| {
+                    let len = prefix
+                        .char_indices()
+                        .zip(o.chars())
+                        .take_while(|((_, a), b)| a == b)
+                        .last()
+                        .map_or(0, |((i, a), _)| i + a.len_utf8());
+                    &prefix[..len]
+                });
+                self.input.set_text(&format!("{}{}", head, common));
+                self.print(LineKind::Output, options.join("  "));
+            }
+        }
+    }
+
+    fn history_back(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => return,
+            Some(pos) => pos - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.input.text().to_owned();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+        self.input.set_text(&self.history[pos]);
+    }
+
+    fn history_forward(&mut self) {
+        match self.history_pos {
+            Some(pos) if pos + 1 < self.history.len() => {
+                self.history_pos = Some(pos + 1);
+                self.input.set_text(&self.history[pos + 1]);
+            }
+            Some(_) => {
+                self.history_pos = None;
+                self.input.set_text(&self.draft);
+            }
+            None => (),
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        if self.slide <= 0.0 {
+            return Ok(());
+        }
+        let theme = world.theme.borrow();
+        let font = theme.font("mono");
+        let scale = theme.sizes.small;
+        let line_height = scale * LINE_SPACING;
+
+        let screen = graphics::screen_coordinates(ctx);
+        let height = screen.h * HEIGHT_FRACTION;
+        let rect = Rect::new(screen.x, screen.y - height * (1.0 - self.slide), screen.w, height);
+        if let Some(panel) = theme.panel("console") {
+            panel.draw(ctx, rect)?;
+        }
+
+        let input_y = rect.bottom() - MARGIN - line_height;
+        let text_offset = (line_height - scale) / 2.0;
+        let prompt = font.colored(PROMPT, scale, theme.palette.selection);
+        let prompt_width = prompt.width(ctx) as f32;
+        graphics::draw(
+            ctx,
+            &prompt,
+            DrawParam::default().dest(Point2::new(rect.x + MARGIN, input_y + text_offset)),
+        )?;
+        let style = ui::TextFieldStyle {
+            font: font.clone(),
+            scale,
+            text_color: theme.palette.text,
+            caret_color: theme.palette.text,
+            selection_color: theme.palette.selection,
+            padding: 0.0,
+        };
+        let field = Rect::new(
+            rect.x + MARGIN + prompt_width,
+            input_y,
+            rect.w - 2.0 * MARGIN - prompt_width,
+            line_height,
+        );
+        self.input.draw(ctx, field, &style)?;
+
+        // Scrollback, newest at the bottom.
+        let rows = ((input_y - rect.y - MARGIN) / line_height).max(0.0) as usize;
+        self.scroll = self.scroll.min(self.scrollback.len().saturating_sub(rows));
+        let end = self.scrollback.len() - self.scroll;
+        let start = end.saturating_sub(rows);
+        for (i, line) in self.scrollback.range(start..end).enumerate() {
+            let color = match line.kind {
+                LineKind::Log => LOG_COLOR,
+                LineKind::Command => theme.palette.selection,
+                LineKind::Output => theme.palette.text,
+                LineKind::Error => theme.palette.error,
+            };
+            let y = input_y - (end - start - i) as f32 * line_height + text_offset;
+            let text = font.colored(&line.text, scale, color);
+            graphics::draw(ctx, &text, DrawParam::default().dest(Point2::new(rect.x + MARGIN, y)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use ggez::event::KeyMods;
+
+    use super::*;
+    use crate::settings::Settings;
+
+    fn world() -> World {
+        let resource_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+        World::headless(&resource_dir, Settings::default(), 0)
+    }
+
+    fn env() -> Env {
+        Env {
+            stack: vec!["TitleScene".to_owned()],
+            registered: vec!["LevelScene".to_owned(), "TitleScene".to_owned()],
+        }
+    }
+
+    fn press(console: &mut Console, world: &mut World, keycode: KeyCode) -> Option<String> {
+        let key = KeyPress {
+            keycode,
+            mods: KeyMods::empty(),
+            repeat: false,
+        };
+        console.key_down(world, &env(), key)
+    }
+
+    fn type_text(console: &mut Console, text: &str) {
+        for c in text.chars() {
+            console.text_input(c);
+        }
+    }
+
+    fn run(console: &mut Console, world: &mut World, line: &str) -> Option<String> {
+        type_text(console, line);
+        press(console, world, KeyCode::Return)
+    }
+
+    fn last_line(console: &Console) -> &str {
+        &console.scrollback.back().expect("nothing printed").text
+    }
+
+    #[test]
+    fn return_runs_the_line_and_prints_what_it_said() {
+        let mut world = world();
+        let mut console = Console::new();
+        assert_eq!(run(&mut console, &mut world, " give hat "), Some("give hat".to_owned()));
+        assert_eq!(console.input.text(), "");
+        assert_eq!(console.scrollback[0].text, "> give hat");
+        assert_eq!(last_line(&console), "Gave hat");
+
+        run(&mut console, &mut world, "fly");
+        assert_eq!(console.scrollback.back().unwrap().kind, LineKind::Error);
+        assert_eq!(run(&mut console, &mut world, "   "), None);
+        assert_eq!(run(&mut console, &mut world, "clear"), None);
+        assert!(console.scrollback.is_empty());
+    }
+
+    #[test]
+    fn the_toggle_key_isnt_typed() {
+        let mut console = Console::new();
+        type_text(&mut console, "`a~\u{7f}");
+        assert_eq!(console.input.text(), "a");
+    }
+
+    #[test]
+    fn up_and_down_go_through_history() {
+        let mut world = world();
+        let mut console = Console::new();
+        run(&mut console, &mut world, "give apple");
+        run(&mut console, &mut world, "give pear");
+        run(&mut console, &mut world, "give pear");
+        type_text(&mut console, "giv");
+
+        press(&mut console, &mut world, KeyCode::Up);
+        assert_eq!(console.input.text(), "give pear");
+        press(&mut console, &mut world, KeyCode::Up);
+        assert_eq!(console.input.text(), "give apple");
+        press(&mut console, &mut world, KeyCode::Up);
+        assert_eq!(console.input.text(), "give apple");
+        press(&mut console, &mut world, KeyCode::Down);
+        assert_eq!(console.input.text(), "give pear");
+        press(&mut console, &mut world, KeyCode::Down);
+        assert_eq!(console.input.text(), "giv");
+    }
+
+    #[test]
+    fn tab_finishes_the_word() {
+        let mut world = world();
+        let mut console = Console::new();
+        type_text(&mut console, "sc");
+        press(&mut console, &mut world, KeyCode::Tab);
+        assert_eq!(console.input.text(), "scene ");
+
+        type_text(&mut console, "p");
+        press(&mut console, &mut world, KeyCode::Tab);
+        assert_eq!(console.input.text(), "scene p");
+        assert_eq!(last_line(&console), "push  pop");
+
+        type_text(&mut console, "us");
+        press(&mut console, &mut world, KeyCode::Tab);
+        type_text(&mut console, "L");
+        press(&mut console, &mut world, KeyCode::Tab);
+        assert_eq!(console.input.text(), "scene push LevelScene ");
+    }
+}
@@ -0,0 +1,129 @@
+//! Crash reports.
+//!
+//! When the game panics, a hook writes what it knows to a file in
+//! `crashes` in the user data directory and tells the player where
+//! it is, so playtesters have something to send us.  The hook can't
+//! get at the game itself, so main keeps a copy of the interesting
+//! bits here with `note_state`.
+
+use std::backtrace::Backtrace;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::panic;
+use std::path;
+use std::sync::Mutex;
+
+use log::*;
+
+use crate::logging;
+use crate::settings::Settings;
+use crate::util;
+
+struct State {
+    scenes: Vec<String>,
+    settings: Option<Settings>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    scenes: Vec::new(),
+    settings: None,
+});
+
+/// Remembers the scene stack and settings for the next crash
+/// report.  Cheap when nothing's changed, so call it every frame.
+pub fn note_state(scenes: &[&str], settings: &Settings) {
+    if let Ok(mut state) = STATE.try_lock() {
+        if state.scenes.iter().map(String::as_str).ne(scenes.iter().cloned()) {
+            state.scenes = scenes.iter().map(|s| (*s).to_owned()).collect();
+        }
+        if state.settings.as_ref() != Some(settings) {
+            state.settings = Some(settings.clone());
+        }
+    }
+}
+
+/// Replaces the panic hook with one that writes a crash report
+/// and then does whatever the old one did.
+pub fn install() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = report(&info.to_string());
+        match write_report(&report) {
+            Ok(path) => {
+                error!("Crashed!  Wrote a report to {:?}", path);
+                tell_player(&path);
+            }
+            Err(e) => error!("Crashed, and could not write a crash report: {}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+fn report(panic: &str) -> String {
+    let mut report = String::new();
+    // Writing to a String can't fail.
+    let _ = write_report_text(&mut report, panic);
+    report
+}
+
+fn write_report_text(report: &mut String, panic: &str) -> fmt::Result {
+    writeln!(report, "Grandpa's Big Adventure {} crashed", env!("CARGO_PKG_VERSION"))?;
+    writeln!(report, "at {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"))?;
+    writeln!(
+        report,
+        "in thread {:?}\n",
+        std::thread::current().name().unwrap_or("<unnamed>")
+    )?;
+    writeln!(report, "{}\n", panic)?;
+
+    // Don't wait on a lock the panicking code might be holding.
+    match STATE.try_lock() {
+        Ok(state) => {
+            writeln!(report, "Scenes: {}\n", state.scenes.join(" > "))?;
+            let settings = state
+                .settings
+                .as_ref()
+                .map(|s| ron::ser::to_string_pretty(s, ron::ser::PrettyConfig::default()));
+            match settings {
+                Some(Ok(settings)) => writeln!(report, "Settings:\n{}\n", settings)?,
+                Some(Err(e)) => writeln!(report, "Settings: could not write them out: {}\n", e)?,
+                None => writeln!(report, "Settings: not loaded yet\n")?,
+            }
+        }
+        Err(_) => writeln!(report, "Scenes and settings: unavailable\n")?,
+    }
+
+    writeln!(report, "Backtrace:\n{}\n", Backtrace::force_capture())?;
+
+    let (lines, _) = logging::recent_lines(0);
+    writeln!(report, "Last {} log lines:", lines.len())?;
+    for line in lines {
+        writeln!(report, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn write_report(report: &str) -> io::Result<path::PathBuf> {
+    let mut path = util::user_data_dir();
+    path.push("crashes");
+    fs::create_dir_all(&path)?;
+    path.push(format!("crash-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Pops up a message box, since the window's probably gone and the
+/// player may not have a terminal open.
+fn tell_player(path: &path::Path) {
+    let message = format!(
+        "Sorry, the game crashed.  A report was saved to\n\n{}\n\nPlease send it to us along with what you were doing.",
+        path.display()
+    );
+    eprintln!("{}", message);
+    tinyfiledialogs::message_box_ok(
+        "Grandpa's Big Adventure crashed",
+        &message,
+        tinyfiledialogs::MessageBoxIcon::Error,
+    );
+}
@@ -0,0 +1,174 @@
+//! An overlay of numbers for figuring out what the game is doing,
+//! drawn on top of every scene.  F3 toggles it.
+//!
+//! While it's up, there are also keys for stopping game time and
+//! stepping through it a tick at a time, or slowing it down.
+
+use std::collections::VecDeque;
+
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder, Rect, Text};
+use ggez::{timer, Context, GameResult};
+use specs::Join;
+
+use crate::input;
+use crate::types::Point2;
+use crate::world::World;
+
+pub const TOGGLE_KEY: KeyCode = KeyCode::F3;
+/// Stops and starts game time.
+pub const PAUSE_KEY: KeyCode = KeyCode::F5;
+/// Runs one tick, pausing first if need be.
+pub const STEP_KEY: KeyCode = KeyCode::F6;
+/// Cycles through slower speeds.
+pub const SLOW_MOTION_KEY: KeyCode = KeyCode::F7;
+
+/// How many frames the graph shows.
+const HISTORY: usize = 120;
+
+const GRAPH_WIDTH: f32 = 240.0;
+const GRAPH_HEIGHT: f32 = 60.0;
+/// Frame time at the top of the graph, in milliseconds.
+const GRAPH_MAX_MS: f32 = 50.0;
+
+const MARGIN: f32 = 10.0;
+
+const BAR_COLOR: Color = Color {
+    r: 0.3,
+    g: 0.9,
+    b: 0.4,
+    a: 1.0,
+};
+const SLOW_BAR_COLOR: Color = Color {
+    r: 0.95,
+    g: 0.3,
+    b: 0.3,
+    a: 1.0,
+};
+const TARGET_LINE_COLOR: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 0.5,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct DebugOverlay {
+    pub visible: bool,
+    /// Milliseconds per frame, oldest first.
+    frame_times: VecDeque<f32>,
+    /// Update ticks run in the most recent frame.
+    ticks: u32,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Notes how the last frame went.  Call once per frame, whether
+    /// or not the overlay is showing, so the graph is full when it's
+    /// turned on.
+    pub fn record_frame(&mut self, ctx: &Context, ticks: u32) {
+        let ms = timer::duration_to_f64(timer::delta(ctx)) as f32 * 1000.0;
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(ms);
+        self.ticks = ticks;
+    }
+
+    pub fn draw(&self, ctx: &mut Context, world: &World, scene_names: &[&str]) -> GameResult<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let theme = world.theme.borrow();
+
+        let text = Text::new(self.report(ctx, world, scene_names));
+        let (text_w, text_h) = text.dimensions(ctx);
+        let panel = Rect::new(
+            MARGIN,
+            MARGIN,
+            GRAPH_WIDTH.max(text_w as f32) + 2.0 * MARGIN,
+            GRAPH_HEIGHT + text_h as f32 + 3.0 * MARGIN,
+        );
+        let graph = Rect::new(panel.x + MARGIN, panel.y + MARGIN, GRAPH_WIDTH, GRAPH_HEIGHT);
+
+        let mut mesh = MeshBuilder::new();
+        mesh.rectangle(DrawMode::fill(), panel, theme.palette.dim);
+        let target_ms = world.settings.tick_seconds() * 1000.0;
+        let bar_width = GRAPH_WIDTH / HISTORY as f32;
+        for (i, &ms) in self.frame_times.iter().enumerate() {
+            let h = (ms / GRAPH_MAX_MS).min(1.0) * GRAPH_HEIGHT;
+            let color = if ms > target_ms * 1.5 {
+                SLOW_BAR_COLOR
+            } else {
+                BAR_COLOR
+            };
+            let bar = Rect::new(graph.x + i as f32 * bar_width, graph.bottom() - h, bar_width, h);
+            mesh.rectangle(DrawMode::fill(), bar, color);
+        }
+        let target_y = graph.bottom() - target_ms / GRAPH_MAX_MS * GRAPH_HEIGHT;
+        mesh.line(
+            &[Point2::new(graph.left(), target_y), Point2::new(graph.right(), target_y)],
+            1.0,
+            TARGET_LINE_COLOR,
+        )?;
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::default())?;
+
+        graphics::draw(
+            ctx,
+            &text,
+            (Point2::new(graph.x, graph.bottom() + MARGIN), theme.palette.text),
+        )
+    }
+
+    fn report(&self, ctx: &Context, world: &World, scene_names: &[&str]) -> String {
+        let (worst, total) = self
+            .frame_times
+            .iter()
+            .fold((0.0f32, 0.0f32), |(worst, total), &ms| (worst.max(ms), total + ms));
+        let average = total / self.frame_times.len().max(1) as f32;
+        let entities = world.specs_world.entities().join().count();
+
+        let buttons: Vec<String> = input::Button::ALL
+            .iter()
+            .filter(|b| world.input.get_button_down(**b))
+            .map(|b| format!("{:?}", b))
+            .collect();
+        let axes: Vec<String> = input::Axis::ALL
+            .iter()
+            .map(|a| format!("{:?} {:+.2}", a, world.input.get_axis(*a)))
+            .collect();
+
+        format!(
+            "FPS: {:.0}  frame: {:.1} ms avg, {:.1} ms worst\n\
+             Ticks this frame: {}  time: {:.2}x{}\n\
+             Entities: {}\n\
+             Scenes: {}\n\
+             Resources loaded: {}\n\
+             Buttons: {}\n\
+             Axes: {}",
+            timer::fps(ctx),
+            average,
+            worst,
+            self.ticks,
+            world.time.scale,
+            if world.time.paused { ", paused" } else { "" },
+            entities,
+            scene_names.join(" > "),
+            world.resources.loaded_count(),
+            if buttons.is_empty() {
+                "-".to_owned()
+            } else {
+                buttons.join(" ")
+            },
+            axes.join("  "),
+        )
+    }
+}
@@ -0,0 +1,15 @@
+//! Dialogue text helpers.
+//!
+//! Lines of dialogue are plain strings that may contain placeholders
+//! in braces, which get filled in from the game state right before
+//! the line is shown.
+
+use crate::game_state::GameState;
+
+/// Replaced with the name the player typed in at the start.
+pub const PLAYER_NAME: &str = "{player_name}";
+
+/// Substitutes every placeholder in `line` with its current value.
+pub fn fill(line: &str, state: &GameState) -> String {
+    line.replace(PLAYER_NAME, state.player_name())
+}
@@ -0,0 +1,102 @@
+//! Per-playthrough game state: everything about the player's
+//! progress that isn't an entity in the specs world.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+/// Longest player name we accept, in display columns (see
+/// `util::display_width`), so a name in a wide script still fits
+/// the name box.
+pub const PLAYER_NAME_MAX_CHARS: usize = 25;
+
+/// The level a new game starts in.
+pub const STARTING_LEVEL: &str = "grandpas_house";
+
+/// This all goes into save files as-is.  Every field is
+/// `#[serde(default)]` so that adding a field doesn't break loading
+/// older saves; see `save` for changes that need more than that.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameState {
+    pub player_name: Option<String>,
+    pub current_level: String,
+    /// Item names, in the order they were picked up.
+    pub inventory: Vec<String>,
+    /// Story progress.  Most flags are just 0 or 1, but some
+    /// count things.
+    pub flags: BTreeMap<String, i32>,
+    /// Seconds spent actually playing, not counting menus.
+    pub play_time: f64,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self {
+            player_name: None,
+            current_level: STARTING_LEVEL.to_owned(),
+            inventory: Vec::new(),
+            flags: BTreeMap::new(),
+            play_time: 0.0,
+        }
+    }
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_flag(&mut self, name: &str, value: i32) {
+        self.flags.insert(name.to_owned(), value);
+    }
+
+    /// The player's name, or an empty string if they
+    /// haven't told us yet.
+    pub fn player_name(&self) -> &str {
+        self.player_name.as_deref().unwrap_or("")
+    }
+
+    /// Validates and stores the player's name.
+    pub fn set_player_name(&mut self, name: &str) -> Result<(), NameError> {
+        let name = validate_player_name(name)?;
+        self.player_name = Some(name);
+        Ok(())
+    }
+}
+
+/// Why a name typed into the name box was rejected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NameError {
+    Empty,
+    TooLong,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            NameError::Empty => write!(f, "Please enter a name."),
+            NameError::TooLong => write!(
+                f,
+                "Names can be at most {} characters.",
+                PLAYER_NAME_MAX_CHARS
+            ),
+        }
+    }
+}
+
+/// Trims surrounding whitespace off a typed name and checks it's
+/// something we can actually call the player.
+pub fn validate_player_name(name: &str) -> Result<String, NameError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        Err(NameError::Empty)
+    } else if util::display_width(trimmed) > PLAYER_NAME_MAX_CHARS {
+        Err(NameError::TooLong)
+    } else {
+        Ok(trimmed.to_owned())
+    }
+}
@@ -0,0 +1,277 @@
+//! Runs the game with no window, for tests.
+//!
+//! A `Sim` owns a scene stack on a headless `World` and a fake clock
+//! that moves one frame per tick, so tests can feed it keys and text
+//! and then look at which scenes are up and what's in the world.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use ggez::event::{KeyCode, KeyMods};
+
+use crate::host::Headless;
+use crate::input;
+use crate::scenes;
+use crate::settings::Settings;
+use crate::world::World;
+
+pub struct Sim {
+    pub scenes: scenes::Stack,
+    pub host: Headless,
+    binding: input::Binding,
+}
+
+impl Sim {
+    /// Starts a game on the named scene.
+    pub fn new(start_scene: &str) -> Self {
+        let resource_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let world = World::headless(&resource_dir, Settings::default(), 0);
+        let mut sim = Sim {
+            scenes: scenes::Stack::new(world),
+            host: Headless::new(),
+            binding: input::create_input_binding(),
+        };
+        assert!(
+            sim.scenes.push_named(&mut sim.host, start_scene),
+            "no scene called {}",
+            start_scene
+        );
+        sim
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.scenes.world
+    }
+
+    /// The scene on top of the stack.
+    pub fn top(&self) -> &str {
+        self.scenes.current().name()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.scenes.names()
+    }
+
+    /// Runs one frame and moves the clock on by as much.
+    pub fn tick(&mut self) {
+        self.scenes.update(&mut self.host);
+        let tick = self.scenes.world.settings.tick_seconds();
+        self.host.advance(Duration::from_secs_f32(tick));
+    }
+
+    pub fn run(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    pub fn key_down(&mut self, keycode: KeyCode) {
+        let press = input::KeyPress {
+            keycode,
+            mods: KeyMods::empty(),
+            repeat: false,
+        };
+        self.scenes.key_down(&self.binding, press);
+    }
+
+    pub fn key_up(&mut self, keycode: KeyCode) {
+        self.scenes.key_up(&self.binding, keycode);
+    }
+
+    /// Presses and lets go of a key, then runs a frame.
+    pub fn press(&mut self, keycode: KeyCode) {
+        self.key_down(keycode);
+        self.key_up(keycode);
+        self.tick();
+    }
+
+    /// Types some text, as if it came in through the window's text
+    /// input, then runs a frame.
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            self.scenes.text_input_event(&mut self.host, c);
+        }
+        self.tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::Join;
+
+    use super::*;
+    use crate::components::{Animation, Behaviour, Npc, Player, Position};
+    use crate::prefab::Prefab;
+
+    fn player_position(sim: &mut Sim) -> Position {
+        let world = &sim.world().specs_world;
+        let (players, positions) = (world.read_storage::<Player>(), world.read_storage::<Position>());
+        let (_, pos) = (&players, &positions).join().next().expect("no player");
+        *pos
+    }
+
+    fn player_animation(sim: &mut Sim) -> Animation {
+        let world = &sim.world().specs_world;
+        let (players, animations) = (world.read_storage::<Player>(), world.read_storage::<Animation>());
+        let (_, anim) = (&players, &animations).join().next().expect("no player");
+        anim.clone()
+    }
+
+    #[test]
+    fn entering_a_name_goes_to_the_title() {
+        let mut sim = Sim::new("UserInputScene");
+        sim.type_text("Grandpa");
+        sim.press(KeyCode::Return);
+        sim.tick();
+        assert_eq!(sim.top(), "TitleScene");
+        assert_eq!(sim.world().game_state.player_name(), "Grandpa");
+    }
+
+    #[test]
+    fn input_waits_for_the_transition_to_finish() {
+        let mut sim = Sim::new("UserInputScene");
+        sim.type_text("Grandpa");
+        sim.press(KeyCode::Return);
+        sim.tick();
+        assert_eq!(sim.top(), "TitleScene");
+        assert!(sim.scenes.is_transitioning());
+
+        // The name screen cross-fades to the title for half a second,
+        // and picking "Start Game" before then does nothing.
+        let tick = sim.world().settings.tick_seconds();
+        let ticks = (0.5 / tick).ceil() as u32;
+        sim.run(ticks - 10);
+        sim.press(KeyCode::C);
+        sim.tick();
+        assert!(sim.scenes.is_transitioning());
+        assert_eq!(sim.names(), vec!["TitleScene"]);
+
+        sim.run(10);
+        assert!(!sim.scenes.is_transitioning());
+        sim.press(KeyCode::C);
+        sim.tick();
+        assert_eq!(sim.names(), vec!["TitleScene", "LevelScene"]);
+    }
+
+    #[test]
+    fn an_empty_name_is_rejected() {
+        let mut sim = Sim::new("UserInputScene");
+        sim.press(KeyCode::Return);
+        sim.run(10);
+        assert_eq!(sim.top(), "UserInputScene");
+    }
+
+    #[test]
+    fn backspace_edits_the_name() {
+        let mut sim = Sim::new("UserInputScene");
+        sim.type_text("Grandma");
+        sim.press(KeyCode::Back);
+        sim.type_text("pa");
+        sim.press(KeyCode::Return);
+        sim.tick();
+        assert_eq!(sim.world().game_state.player_name(), "Grandmpa");
+    }
+
+    #[test]
+    fn menu_pauses_the_level_and_back_resumes() {
+        let mut sim = Sim::new("LevelScene");
+        sim.press(KeyCode::Z);
+        sim.tick();
+        assert_eq!(sim.names(), vec!["LevelScene", "PauseScene"]);
+        sim.press(KeyCode::X);
+        sim.tick();
+        assert_eq!(sim.names(), vec!["LevelScene"]);
+    }
+
+    #[test]
+    fn quit_to_title_works_without_a_title_underneath() {
+        // Like starting with `--load` or `--scene LevelScene`.
+        let mut sim = Sim::new("LevelScene");
+        sim.press(KeyCode::Z);
+        sim.tick();
+        assert_eq!(sim.names(), vec!["LevelScene", "PauseScene"]);
+        for _ in 0..3 {
+            sim.press(KeyCode::Down);
+        }
+        sim.press(KeyCode::C);
+        sim.tick();
+        assert_eq!(sim.names(), vec!["TitleScene"]);
+    }
+
+    #[test]
+    fn holding_right_walks_the_player_right() {
+        let mut sim = Sim::new("LevelScene");
+        let start = player_position(&mut sim);
+        sim.key_down(KeyCode::Right);
+        sim.run(60);
+        sim.key_up(KeyCode::Right);
+        let end = player_position(&mut sim);
+        assert!(end.x > start.x, "player didn't move: {:?} -> {:?}", start, end);
+        assert_eq!(end.y, start.y);
+    }
+
+    #[test]
+    fn walking_and_running_change_the_players_animation() {
+        let mut sim = Sim::new("LevelScene");
+        sim.tick();
+        assert_eq!(player_animation(&mut sim).clip, "idle");
+        sim.key_down(KeyCode::Right);
+        sim.run(2);
+        assert_eq!(player_animation(&mut sim).clip, "walk");
+        sim.key_down(KeyCode::LShift);
+        sim.run(2);
+        assert_eq!(player_animation(&mut sim).clip, "run");
+        sim.key_up(KeyCode::LShift);
+        sim.key_up(KeyCode::Right);
+        sim.run(2);
+        assert_eq!(player_animation(&mut sim).clip, "idle");
+    }
+
+    #[test]
+    fn wandering_npcs_stay_near_home() {
+        let mut sim = Sim::new("LevelScene");
+        let home = Position::new(1000.0, 1000.0);
+        let neighbour = sim
+            .world()
+            .spawn("neighbour", &Prefab::at(home.x, home.y))
+            .expect("no neighbour prefab");
+        let radius = match sim.world().specs_world.read_storage::<Npc>().get(neighbour) {
+            Some(Npc {
+                behaviour: Behaviour::Wander { radius },
+                ..
+            }) => *radius,
+            other => panic!("the neighbour doesn't wander: {:?}", other),
+        };
+
+        // `Sim` always uses seed 0, so this is the same minute of
+        // wandering every run.
+        let mut farthest: f32 = 0.0;
+        for _ in 0..60 * 60 {
+            sim.tick();
+            let positions = sim.world().specs_world.read_storage::<Position>();
+            let pos = positions.get(neighbour).expect("neighbour vanished");
+            farthest = farthest.max((pos.x - home.x).hypot(pos.y - home.y));
+        }
+        // Far enough that it's been for a walk, with a little slack
+        // for rounding on the last step.
+        assert!(farthest > radius / 4.0, "only got {} from home", farthest);
+        assert!(farthest <= radius + 0.01, "got {} from home", farthest);
+    }
+
+    #[test]
+    fn select_next_to_an_npc_talks_to_them() {
+        let mut sim = Sim::new("LevelScene");
+        sim.world().spawn("kiwi", &Prefab::at(20.0, 0.0)).expect("no kiwi prefab");
+        sim.press(KeyCode::C);
+        assert_eq!(sim.top(), "DialogueScene");
+
+        // The first press shows the whole line, the next one moves on.
+        for _ in 0..10 {
+            if sim.top() != "DialogueScene" {
+                break;
+            }
+            sim.press(KeyCode::C);
+        }
+        assert_eq!(sim.names(), vec!["LevelScene"]);
+    }
+}
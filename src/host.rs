@@ -0,0 +1,58 @@
+//! What the game is running on: a ggez window, or nothing at all.
+//!
+//! Scenes update against a `Host` rather than a ggez `Context` so the
+//! game can run headless for tests.  Anything that really needs the
+//! window (drawing, changing the window mode, loading through ggez's
+//! filesystem) asks for the context with `ggez()` and skips itself
+//! when there isn't one.
+
+use std::time::Duration;
+
+use ggez::{timer, Context};
+
+pub trait Host {
+    /// The ggez context, if there's a window.
+    fn ggez(&mut self) -> Option<&mut Context>;
+
+    /// How long the game's been running.
+    fn time_since_start(&self) -> Duration;
+}
+
+impl Host for Context {
+    fn ggez(&mut self) -> Option<&mut Context> {
+        Some(self)
+    }
+
+    fn time_since_start(&self) -> Duration {
+        timer::time_since_start(self)
+    }
+}
+
+/// No window, and a clock that only moves when told to.
+#[cfg(test)]
+#[derive(Debug, Clone, Default)]
+pub struct Headless {
+    now: Duration,
+}
+
+#[cfg(test)]
+impl Headless {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&mut self, by: Duration) {
+        self.now += by;
+    }
+}
+
+#[cfg(test)]
+impl Host for Headless {
+    fn ggez(&mut self) -> Option<&mut Context> {
+        None
+    }
+
+    fn time_since_start(&self) -> Duration {
+        self.now
+    }
+}
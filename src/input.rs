@@ -9,19 +9,46 @@ pub enum Button {
     Delete,
     Menu,
     Quit,
+    /// Held down to run instead of walk.
+    Run,
     Select,
 }
 
+impl Button {
+    pub const ALL: [Button; 7] = [
+        Button::Back,
+        Button::Confirm,
+        Button::Delete,
+        Button::Menu,
+        Button::Quit,
+        Button::Run,
+        Button::Select,
+    ];
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Axis {
     Vert,
     Horz,
 }
 
+impl Axis {
+    pub const ALL: [Axis; 2] = [Axis::Vert, Axis::Horz];
+}
+
 pub type Binding = input::InputBinding<Axis, Button>;
 pub type Event = input::InputEffect<Axis, Button>;
 pub type State = input::InputState<Axis, Button>;
 
+/// A raw key press, for the few things (like text fields) that need
+/// keys which aren't worth a `Button` binding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyPress {
+    pub keycode: KeyCode,
+    pub mods: KeyMods,
+    pub repeat: bool,
+}
+
 /// Create the default keybindings for our input state.
 pub fn create_input_binding() -> input::InputBinding<Axis, Button> {
     input::InputBinding::new()
@@ -34,5 +61,6 @@ pub fn create_input_binding() -> input::InputBinding<Axis, Button> {
         .bind_key_to_button(KeyCode::Z, Button::Menu)
         .bind_key_to_button(KeyCode::Back, Button::Delete)
         .bind_key_to_button(KeyCode::Escape, Button::Quit)
+        .bind_key_to_button(KeyCode::LShift, Button::Run)
         .bind_key_to_button(KeyCode::Return, Button::Confirm)
 }
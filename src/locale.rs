@@ -0,0 +1,304 @@
+//! Translated UI text.
+//!
+//! Each language has a string table in `/locale/<code>.ron` mapping
+//! keys to text.  Text can have `{name}` placeholders, filled in from
+//! the arguments passed to `Strings::format`, and an entry can give
+//! several plural forms instead of one string:
+//!
+//! ```ron
+//! "slot.play_time": { "one": "{count} minute", "other": "{count} minutes" },
+//! ```
+//!
+//! Keys missing from the current language come from English, and
+//! each one gets a warning the first time it's asked for.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path;
+
+use ggez::Context;
+use log::*;
+use serde::Deserialize;
+
+use crate::host::Host;
+use crate::resources::{self, Key, Loaded, Storage};
+use crate::types::Error;
+
+/// The language every other one falls back to.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// Languages offered on the options screen, as code and the
+/// language's own name for itself.
+pub const LANGUAGES: &[(&str, &str)] = &[("en", "English"), ("de", "Deutsch")];
+
+fn table_path(language: &str) -> path::PathBuf {
+    path::PathBuf::from(format!("/locale/{}.ron", language))
+}
+
+/// Reads a string table straight off the disk, for when there's no
+/// ggez filesystem to go through.
+fn read_table(resource_dir: &path::Path, language: &str) -> Result<StringTable, String> {
+    resources::read_ron(resource_dir, table_path(language))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    /// Plural category (`one`, `few`, `many`, `other`...) to text.
+    Plural(BTreeMap<String, String>),
+}
+
+/// One language's worth of text.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(transparent)]
+pub struct StringTable(BTreeMap<String, Entry>);
+
+impl StringTable {
+    fn text(&self, key: &str) -> Option<&str> {
+        match self.0.get(key)? {
+            Entry::Text(s) => Some(s.as_str()),
+            Entry::Plural(forms) => forms.get("other").map(String::as_str),
+        }
+    }
+
+    fn plural(&self, key: &str, category: &str) -> Option<&str> {
+        match self.0.get(key)? {
+            Entry::Text(s) => Some(s.as_str()),
+            Entry::Plural(forms) => forms
+                .get(category)
+                .or_else(|| forms.get("other"))
+                .map(String::as_str),
+        }
+    }
+}
+
+impl warmy::Load<Context, Key> for StringTable {
+    type Error = Error;
+    fn load(key: Key, _storage: &mut Storage, ctx: &mut Context) -> Result<Loaded<Self>, Self::Error> {
+        resources::load_ron_resource(key, ctx)
+    }
+}
+
+/// Which plural form a count takes in a language, named the way the
+/// Unicode CLDR names them.
+pub fn plural_category(language: &str, n: i64) -> &'static str {
+    let n = n.abs();
+    let (rem10, rem100) = (n % 10, n % 100);
+    let few = (2..=4).contains(&rem10) && !(12..=14).contains(&rem100);
+    match language {
+        "ja" | "ko" | "zh" => "other",
+        "fr" => {
+            if n <= 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+        "ru" | "uk" => {
+            if rem10 == 1 && rem100 != 11 {
+                "one"
+            } else if few {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        "pl" => {
+            if n == 1 {
+                "one"
+            } else if few {
+                "few"
+            } else {
+                "many"
+            }
+        }
+        _ => {
+            if n == 1 {
+                "one"
+            } else {
+                "other"
+            }
+        }
+    }
+}
+
+/// Replaces each `{name}` in `template` with the matching argument.
+/// Placeholders without an argument are left alone, so they can be
+/// filled in later (see `dialogue::fill`).  The template is only
+/// read once, so braces in the arguments (a player who called
+/// themselves `{when}`, say) come out as they went in.
+pub fn interpolate(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let name = after.find('}').map(|close| &after[..close]);
+        match name.and_then(|name| args.iter().find(|(arg, _)| *arg == name)) {
+            Some((name, value)) => {
+                text.push_str(&value.to_string());
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// The string tables in use: the chosen language and English.
+pub struct Strings {
+    language: String,
+    /// `None` when the chosen language is English, or failed to load.
+    table: Option<warmy::Res<StringTable>>,
+    fallback: warmy::Res<StringTable>,
+    /// Keys we've already complained about, so a missing key drawn
+    /// every frame only shows up in the log once.
+    warned: RefCell<BTreeSet<String>>,
+    /// Where to read tables from when running headless.  `None`
+    /// means going through the resource store.
+    headless_dir: Option<path::PathBuf>,
+}
+
+impl Strings {
+    pub fn new(ctx: &mut Context, store: &mut resources::Store, language: &str) -> Self {
+        let fallback = store
+            .get::<StringTable>(&Key::from_path(table_path(FALLBACK_LANGUAGE)), ctx)
+            .expect("Could not load the English string table");
+        let mut strings = Self {
+            language: FALLBACK_LANGUAGE.to_owned(),
+            table: None,
+            fallback,
+            warned: RefCell::new(BTreeSet::new()),
+            headless_dir: None,
+        };
+        strings.set_language(ctx, store, language);
+        strings
+    }
+
+    /// Strings read from `resource_dir` without ggez.  They don't
+    /// reload when the files change.
+    #[cfg(test)]
+    pub fn headless(resource_dir: &path::Path, store: &mut resources::Store, language: &str) -> Self {
+        let fallback = read_table(resource_dir, FALLBACK_LANGUAGE).expect("Could not load the English string table");
+        let mut strings = Self {
+            language: FALLBACK_LANGUAGE.to_owned(),
+            table: None,
+            fallback: warmy::Res::new(fallback),
+            warned: RefCell::new(BTreeSet::new()),
+            headless_dir: Some(resource_dir.to_owned()),
+        };
+        strings.set_language(&mut crate::host::Headless::new(), store, language);
+        strings
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Switches to another language.  If its table can't be loaded,
+    /// everything stays in English.
+    pub fn set_language(&mut self, ctx: &mut dyn Host, store: &mut resources::Store, language: &str) {
+        info!("Switching language to {:?}", language);
+        self.language = language.to_owned();
+        self.warned.borrow_mut().clear();
+        self.table = None;
+        if language == FALLBACK_LANGUAGE {
+            return;
+        }
+        let table = match (&self.headless_dir, ctx.ggez()) {
+            (Some(dir), _) => read_table(dir, language).map(warmy::Res::new),
+            (None, Some(ctx)) => store
+                .get::<StringTable>(&Key::from_path(table_path(language)), ctx)
+                .map_err(|e| e.to_string()),
+            (None, None) => Err("there's nowhere to load it from".to_owned()),
+        };
+        match table {
+            Ok(table) => self.table = Some(table),
+            Err(e) => warn!("Could not load strings for {:?}, using English: {}", language, e),
+        }
+    }
+
+    /// The text for `key`, as is.
+    pub fn get(&self, key: &str) -> String {
+        self.lookup(key, |table| table.text(key).map(str::to_owned))
+    }
+
+    /// The text for `key` with its placeholders filled in.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        interpolate(&self.get(key), args)
+    }
+
+    /// The form of `key` that goes with `count`, with `{count}` and
+    /// the other placeholders filled in.
+    pub fn plural(&self, key: &str, count: i64, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let mut all_args: Vec<(&str, &dyn fmt::Display)> = vec![("count", &count)];
+        all_args.extend_from_slice(args);
+
+        let category = plural_category(&self.language, count);
+        let fallback_category = plural_category(FALLBACK_LANGUAGE, count);
+        let template = match self.table.as_ref().and_then(|t| t.borrow().plural(key, category).map(str::to_owned)) {
+            Some(text) => text,
+            None => self.lookup(key, |table| table.plural(key, fallback_category).map(str::to_owned)),
+        };
+        interpolate(&template, &all_args)
+    }
+
+    /// Tries the current table, then English, then gives up and
+    /// shows the key itself.
+    fn lookup<F>(&self, key: &str, find: F) -> String
+    where
+        F: Fn(&StringTable) -> Option<String>,
+    {
+        if let Some(text) = self.table.as_ref().and_then(|t| find(&t.borrow())) {
+            return text;
+        }
+        let found = find(&self.fallback.borrow());
+        let missing = found.is_none() || self.language != FALLBACK_LANGUAGE;
+        if missing && self.warned.borrow_mut().insert(key.to_owned()) {
+            match found {
+                Some(_) => warn!("No {:?} text for {:?}, using English", self.language, key),
+                None => warn!("No text for {:?} in any language", key),
+            }
+        }
+        found.unwrap_or_else(|| key.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_filled_in() {
+        let text = interpolate("{count} of {max}", &[("count", &3), ("max", &"5")]);
+        assert_eq!(text, "3 of 5");
+    }
+
+    #[test]
+    fn placeholders_without_arguments_are_left_alone() {
+        let text = interpolate("Hi {player}, {n} {", &[("n", &1)]);
+        assert_eq!(text, "Hi {player}, 1 {");
+    }
+
+    #[test]
+    fn arguments_are_not_filled_in_again() {
+        let template = "Slot {slot}: {name} - {level} ({when}, {play_time})";
+        let text = interpolate(
+            template,
+            &[
+                ("slot", &1),
+                ("name", &"{when}"),
+                ("level", &"garden"),
+                ("when", &"yesterday"),
+                ("play_time", &"{name}"),
+            ],
+        );
+        assert_eq!(text, "Slot 1: {when} - garden (yesterday, {name})");
+    }
+}
@@ -0,0 +1,316 @@
+//! Logging setup, and the recent-log buffer the console reads.
+//!
+//! What gets logged is set by a filter like
+//! `warn,grandpas_big_adventure::scenes=trace`: a level for
+//! everything, then levels for particular modules.  It comes from
+//! `--log-level` if given, else the `GBA_LOG` environment variable,
+//! else `log_filter` in the settings file, else `DEFAULT_FILTER`.
+//!
+//! Logs go to stdout and to `logs/game.log` in the user data
+//! directory.  Each launch moves the last few logs along to
+//! `game.1.log`, `game.2.log` and so on.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use log::LevelFilter;
+
+use crate::util;
+
+pub const ENV_VAR: &str = "GBA_LOG";
+
+/// Our own modules at Info, everything else only when something
+/// is wrong.  gfx is noisy even at Warn.
+pub const DEFAULT_FILTER: &str = "warn,grandpas_big_adventure=info,gfx=off";
+
+/// How many old log files to keep around besides the current one.
+const OLD_LOGS_KEPT: usize = 4;
+
+/// How many log lines `recent_lines` remembers.
+const HISTORY_LEN: usize = 500;
+
+/// A level for everything, plus levels for particular modules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub level: LevelFilter,
+    /// Module path and level, applying to the module and everything
+    /// in it.
+    pub modules: Vec<(String, LevelFilter)>,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        DEFAULT_FILTER.parse().expect("DEFAULT_FILTER doesn't parse")
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Parses comma-separated `level` and `module=level` entries.
+    /// Later entries win.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter {
+            level: LevelFilter::Warn,
+            modules: Vec::new(),
+        };
+        let parse_level = |level: &str| {
+            level
+                .trim()
+                .parse::<LevelFilter>()
+                .map_err(|_| format!("{:?} isn't a log level", level.trim()))
+        };
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.find('=') {
+                Some(i) => {
+                    let module = entry[..i].trim().to_owned();
+                    let level = parse_level(&entry[i + 1..])?;
+                    filter.modules.retain(|(m, _)| *m != module);
+                    filter.modules.push((module, level));
+                }
+                None => filter.level = parse_level(entry)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.level.to_string().to_lowercase())?;
+        for (module, level) in &self.modules {
+            write!(f, ",{}={}", module, level.to_string().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Picks the filter to use, from most to least specific source.
+/// Broken ones are skipped, with what went wrong returned so it can
+/// be logged once logging works.
+pub fn choose_filter(flag: Option<&Filter>, config: Option<&str>) -> (Filter, Vec<String>) {
+    let mut problems = Vec::new();
+    if let Some(filter) = flag {
+        return (filter.clone(), problems);
+    }
+    let env = std::env::var(ENV_VAR).ok();
+    let sources = [(ENV_VAR, env.as_deref()), ("log_filter in the settings", config)];
+    for (source, spec) in sources.iter() {
+        if let Some(spec) = spec {
+            match spec.parse() {
+                Ok(filter) => return (filter, problems),
+                Err(e) => problems.push(format!("Ignoring {}: {}", source, e)),
+            }
+        }
+    }
+    (Filter::default(), problems)
+}
+
+/// The last few log lines, and how many lines have ever been
+/// logged, so readers can tell which ones they've already seen.
+#[derive(Default)]
+struct History {
+    lines: VecDeque<String>,
+    total: u64,
+}
+
+/// `None` until the first line is logged, since `VecDeque::new`
+/// can't be called in a static before Rust 1.68.
+static HISTORY: Mutex<Option<History>> = Mutex::new(None);
+
+/// Drops the line rather than waiting if the history is busy, since
+/// the crash reporter logs from the panic hook, maybe while a panic
+/// in here is holding the lock.
+fn remember(line: String) {
+    if let Ok(mut history) = HISTORY.try_lock() {
+        let history = history.get_or_insert_with(History::default);
+        if history.lines.len() == HISTORY_LEN {
+            history.lines.pop_front();
+        }
+        history.lines.push_back(line);
+        history.total += 1;
+    }
+}
+
+/// Log lines logged after the first `seen` ever, as far back as we
+/// remember, plus the new count to pass as `seen` next time.  Gives
+/// nothing rather than waiting if another thread is logging, since
+/// the crash reporter can't risk waiting on a panicked logger.
+pub fn recent_lines(seen: u64) -> (Vec<String>, u64) {
+    match HISTORY.try_lock() {
+        Ok(history) => match history.as_ref() {
+            Some(history) => {
+                let new = (history.total - seen.min(history.total)) as usize;
+                let skip = history.lines.len().saturating_sub(new);
+                (history.lines.iter().skip(skip).cloned().collect(), history.total)
+            }
+            None => (Vec::new(), seen),
+        },
+        Err(_) => (Vec::new(), seen),
+    }
+}
+
+pub fn log_dir() -> path::PathBuf {
+    let mut dir = util::user_data_dir();
+    dir.push("logs");
+    dir
+}
+
+fn log_path(dir: &path::Path, generation: usize) -> path::PathBuf {
+    match generation {
+        0 => dir.join("game.log"),
+        n => dir.join(format!("game.{}.log", n)),
+    }
+}
+
+/// Moves `game.log` to `game.1.log`, that to `game.2.log` and so
+/// on, dropping the oldest.
+fn rotate_logs(dir: &path::Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let oldest = log_path(dir, OLD_LOGS_KEPT);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for generation in (0..OLD_LOGS_KEPT).rev() {
+        let from = log_path(dir, generation);
+        if from.exists() {
+            fs::rename(&from, log_path(dir, generation + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets up `fern` to log to stdout, the log file and the recent-log
+/// buffer, and initializes `log`.
+pub fn setup(filter: &Filter) {
+    use fern::colors::{Color, ColoredLevelConfig};
+    let colors = ColoredLevelConfig::default()
+        .info(Color::Green)
+        .debug(Color::BrightMagenta)
+        .trace(Color::BrightBlue);
+
+    let mut dispatch = fern::Dispatch::new().level(filter.level);
+    for (module, level) in &filter.modules {
+        dispatch = dispatch.level_for(module.clone(), *level);
+    }
+    dispatch = dispatch
+        .chain(
+            fern::Dispatch::new()
+                .format(move |out, message, record| {
+                    out.finish(format_args!(
+                        "[{}][{:<5}][{}] {}",
+                        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        colors.color(record.level()),
+                        record.target(),
+                        message
+                    ))
+                })
+                .chain(std::io::stdout()),
+        )
+        // Keeps recent lines around for the in-game console,
+        // without the terminal colors.
+        .chain(fern::Output::call(|record| {
+            remember(format!("[{:<5}][{}] {}", record.level(), record.target(), record.args()))
+        }));
+
+    let dir = log_dir();
+    let file = rotate_logs(&dir).and_then(|()| fern::log_file(log_path(&dir, 0)));
+    let file_error = match file {
+        Ok(file) => {
+            dispatch = dispatch.chain(
+                fern::Dispatch::new()
+                    .format(|out, message, record| {
+                        out.finish(format_args!(
+                            "[{}][{:<5}][{}] {}",
+                            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                            record.level(),
+                            record.target(),
+                            message
+                        ))
+                    })
+                    .chain(file),
+            );
+            None
+        }
+        Err(e) => Some(e),
+    };
+
+    dispatch.apply().expect("Could not init logging!");
+    log::info!("Logging with filter {}", filter);
+    match file_error {
+        Some(e) => log::warn!("Could not open a log file in {:?}, logging to stdout only: {}", dir, e),
+        None => log::info!("Logging to {:?}", log_path(&dir, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(filter: &Filter) -> Vec<(&str, LevelFilter)> {
+        filter.modules.iter().map(|(m, l)| (m.as_str(), *l)).collect()
+    }
+
+    #[test]
+    fn filters_have_a_level_and_module_levels() {
+        let filter: Filter = " info, grandpas_big_adventure::scenes=TRACE ,gfx=off".parse().unwrap();
+        assert_eq!(filter.level, LevelFilter::Info);
+        assert_eq!(
+            modules(&filter),
+            vec![("grandpas_big_adventure::scenes", LevelFilter::Trace), ("gfx", LevelFilter::Off)]
+        );
+
+        let empty: Filter = "".parse().unwrap();
+        assert_eq!((empty.level, empty.modules.len()), (LevelFilter::Warn, 0));
+    }
+
+    #[test]
+    fn later_filter_entries_win() {
+        let filter: Filter = "debug,gfx=info,winit=warn,gfx=error,off".parse().unwrap();
+        assert_eq!(filter.level, LevelFilter::Off);
+        assert_eq!(modules(&filter), vec![("winit", LevelFilter::Warn), ("gfx", LevelFilter::Error)]);
+    }
+
+    #[test]
+    fn bad_levels_dont_parse() {
+        assert_eq!("loud".parse::<Filter>(), Err("\"loud\" isn't a log level".to_owned()));
+        assert!("info,gfx=".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn filters_print_the_way_they_parse() {
+        assert_eq!(Filter::default().to_string(), DEFAULT_FILTER);
+    }
+
+    // Everything touching the environment variable is in one test,
+    // so tests running at the same time can't trip over it.
+    #[test]
+    fn the_flag_beats_the_environment_beats_the_settings() {
+        let flag: Filter = "trace".parse().unwrap();
+        let level = |(filter, _): (Filter, Vec<String>)| filter.level;
+
+        std::env::remove_var(ENV_VAR);
+        assert_eq!(choose_filter(None, None), (Filter::default(), Vec::new()));
+        assert_eq!(level(choose_filter(None, Some("error"))), LevelFilter::Error);
+        assert_eq!(level(choose_filter(Some(&flag), Some("error"))), LevelFilter::Trace);
+
+        std::env::set_var(ENV_VAR, "debug");
+        assert_eq!(level(choose_filter(None, Some("error"))), LevelFilter::Debug);
+        assert_eq!(level(choose_filter(Some(&flag), Some("error"))), LevelFilter::Trace);
+
+        // Broken ones are passed over, with a note why.
+        std::env::set_var(ENV_VAR, "loud");
+        let (filter, problems) = choose_filter(None, Some("error"));
+        assert_eq!(filter.level, LevelFilter::Error);
+        assert_eq!(problems.len(), 1);
+        let (filter, problems) = choose_filter(None, Some("quiet"));
+        assert_eq!(filter, Filter::default());
+        assert_eq!(problems.len(), 2);
+        std::env::remove_var(ENV_VAR);
+    }
+}
@@ -3,12 +3,35 @@ use std::path;
 
 use ggez::{self, *};
 use ggez::nalgebra::Point2;
+use log::*;
 
 
+mod animation;
+mod cli;
+mod components;
+mod console;
+mod crash;
+mod debug;
+mod dialogue;
+mod game_state;
+#[cfg(test)]
+mod headless;
+mod host;
 mod input;
+mod locale;
+mod logging;
+mod prefab;
 mod resources;
+mod render;
+mod replay;
+mod save;
 mod scenes;
+mod screenshot;
+mod settings;
+mod systems;
+mod timestep;
 mod types;
+mod ui;
 mod util;
 mod world;
 
@@ -17,77 +40,265 @@ struct WindowSettings {
 }
 
 struct MainState {
+    console: console::Console,
+    debug_overlay: debug::DebugOverlay,
     input_binding: input::Binding,
     scenes: scenes::Stack,
     window_settings: WindowSettings,
+    timestep: timestep::FixedTimestep,
+    /// Update ticks run since the game started.
+    ticks: u64,
+    /// Input being recorded, and where to write it when we quit.
+    recording: Option<(replay::Recording, path::PathBuf)>,
+    /// Input being played back instead of the keyboard's.
+    playback: Option<replay::Playback>,
 }
 
 impl MainState {
-    fn new(ctx: &mut Context, resource_path: &path::Path) -> Self {
-        let world = world::World::new(resource_path);
-        let mut scenestack = scenes::Stack::new(ctx, world);
-        // let initial_scene = Box::new(scenes::title::TitleScene::new(ctx, &mut scenestack.world));
-        let initial_scene = Box::new(scenes::useript::UserInputScene::new(ctx, &mut scenestack.world));
-        scenestack.push(initial_scene);
+    fn new(
+        ctx: &mut Context,
+        resource_path: &path::Path,
+        settings: settings::Settings,
+        args: &cli::Args,
+    ) -> Self {
+        let playback = args.replay.as_ref().and_then(|path| match replay::Recording::load(path) {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                error!("Could not read recording {:?}: {}", path, e);
+                None
+            }
+        });
+        let seed = match (&playback, args.seed) {
+            (Some(recording), Some(seed)) if seed != recording.seed => {
+                warn!("Ignoring --seed {}; the recording was made with {}", seed, recording.seed);
+                recording.seed
+            }
+            (Some(recording), _) => recording.seed,
+            (None, Some(seed)) => seed,
+            (None, None) => rand::random(),
+        };
+        info!("Random seed: {}", seed);
+
+        let timestep = timestep::FixedTimestep::new(settings.tick_rate, settings.max_catch_up_ticks);
+        let mut world = world::World::new(ctx, resource_path, settings, seed);
+        let mut start_scene = "UserInputScene";
+        if let Some(number) = args.load {
+            match save::load(&mut world, number - 1) {
+                Ok(true) => start_scene = "LevelScene",
+                Ok(false) => error!("Save slot {} is empty", number),
+                Err(e) => error!("Could not load slot {}: {}", number, e),
+            }
+        }
+        let start_scene = args.scene.as_ref().map_or(start_scene, String::as_str);
+
+        let mut scenestack = scenes::Stack::new(world);
+        if !scenestack.push_named(ctx, start_scene) {
+            let names: Vec<&str> = scenestack.registry().names().collect();
+            error!("No scene {:?}, starting at the beginning.  There's {}", start_scene, names.join(", "));
+            scenestack.push_named(ctx, "UserInputScene");
+        }
 
         Self {
+            console: console::Console::new(),
+            debug_overlay: debug::DebugOverlay::new(),
             input_binding: input::create_input_binding(),
             scenes: scenestack,
             window_settings: WindowSettings {
                 resize_projection: false,
             },
+            timestep,
+            ticks: 0,
+            recording: args.record.clone().map(|path| (replay::Recording::new(seed), path)),
+            playback: playback.map(replay::Playback::new),
+        }
+    }
+
+    /// Notes input for the recording, if we're making one.
+    fn record(&mut self, input: replay::Input) {
+        if let Some((recording, _)) = self.recording.as_mut() {
+            recording.push(self.ticks, input);
+        }
+    }
+
+    fn press_key(&mut self, press: input::KeyPress) {
+        self.scenes.key_down(&self.input_binding, press);
+    }
+
+    fn release_key(&mut self, keycode: event::KeyCode) {
+        if !self.console.is_open() {
+            self.scenes.key_up(&self.input_binding, keycode);
+        } else if let Some(ev) = self.input_binding.resolve(keycode) {
+            // Still let go of buttons held down when the console
+            // opened, so they don't stick.
+            self.scenes.world.input.update_effect(ev, false);
+        }
+    }
+
+    /// Handles the debug keys for pausing, stepping and slowing
+    /// down game time, which only work with the debug overlay up.
+    /// Says whether `keycode` was one of them.
+    fn time_key(&mut self, keycode: event::KeyCode, repeat: bool) -> bool {
+        let time = &mut self.scenes.world.time;
+        match keycode {
+            debug::PAUSE_KEY if !repeat => {
+                time.toggle_pause();
+                info!("Game time {}", if time.paused { "paused" } else { "resumed" });
+            }
+            // Holding it down steps at the key repeat rate.
+            debug::STEP_KEY => {
+                time.step(1);
+            }
+            debug::SLOW_MOTION_KEY if !repeat => {
+                time.cycle_slow_motion();
+                info!("Game time at {}x", time.scale);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Feeds in recorded input due before the next tick.
+    fn play_back(&mut self, ctx: &mut Context) {
+        let due = match self.playback.as_mut() {
+            Some(playback) => playback.due(self.ticks),
+            None => return,
+        };
+        for input in due {
+            match input {
+                replay::Input::KeyDown { key, mods, repeat } => self.press_key(input::KeyPress {
+                    keycode: key,
+                    mods: replay::mods(mods),
+                    repeat,
+                }),
+                replay::Input::KeyUp { key, .. } => self.release_key(key),
+                replay::Input::Text(c) => self.scenes.text_input_event(ctx, c),
+                replay::Input::TimeKey { key, repeat } => {
+                    self.time_key(key, repeat);
+                }
+                replay::Input::Command(line) => self.replay_command(&line),
+            }
+        }
+    }
+
+    /// Runs a recorded console command, logging what it said since
+    /// the console isn't showing it.
+    fn replay_command(&mut self, line: &str) {
+        let env = console::Env::new(&self.scenes);
+        match console::commands::execute(&mut self.scenes.world, &env, line) {
+            Ok(output) => info!("Replayed `{}`: {}", line, output.join("; ")),
+            Err(e) => warn!("Replayed `{}`: {}", line, e),
         }
     }
+
+    /// Whether input is coming from a recording rather than the
+    /// keyboard.
+    fn replaying(&self) -> bool {
+        self.playback.as_ref().map_or(false, |p| !p.finished())
+    }
 }
 
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        const DESIRED_FPS: u32 = 60;
-        while timer::check_update_time(ctx, DESIRED_FPS) {
+        // Recorded input can pause or step game time, so it has to
+        // come in before working out how many ticks to run, or a
+        // replay that paused would never get to its steps.
+        self.play_back(ctx);
+        let ticks = self
+            .timestep
+            .advance(timer::delta(ctx), &mut self.scenes.world.time);
+        for _ in 0..ticks {
+            self.play_back(ctx);
             self.scenes.update(ctx);
+            self.ticks += 1;
         }
+        self.debug_overlay.record_frame(ctx, ticks);
+        self.console.update(ctx);
+        crash::note_state(&self.scenes.names(), &self.scenes.world.settings);
         self.scenes.world.resources.sync(ctx);
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-        graphics::clear(ctx, graphics::Color::from((0.0, 0.0, 0.4, 0.0)));
-        self.scenes.draw(ctx);
+        let clear_color = self.scenes.world.theme.borrow().palette.clear;
+        graphics::clear(ctx, clear_color);
+        self.scenes.draw(ctx, self.timestep.alpha())?;
 
-        let fps = timer::fps(ctx);
-        let fps_display = graphics::Text::new(format!("FPS: {}", fps));
+        if self.scenes.world.settings.show_fps {
+            let fps = timer::fps(ctx);
+            let fps_display = graphics::Text::new(
+                self.scenes.world.strings.format("debug.fps", &[("fps", &fps)]),
+            );
+            let text_color = self.scenes.world.theme.borrow().palette.text;
 
-        graphics::draw(
-            ctx,
-            &fps_display,
-            (Point2::new(50.0, 550.0), graphics::WHITE),
-        )?;
+            graphics::draw(
+                ctx,
+                &fps_display,
+                (Point2::new(50.0, 550.0), text_color),
+            )?;
+        }
+
+        let names = self.scenes.names();
+        self.debug_overlay.draw(ctx, &self.scenes.world, &names)?;
+        self.console.draw(ctx, &self.scenes.world)?;
         graphics::present(ctx)
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: event::KeyCode,
-        _keymod: event::KeyMods,
-        _repeat: bool,
+        keymod: event::KeyMods,
+        repeat: bool,
     ) {
-        if let Some(ev) = self.input_binding.resolve(keycode) {
-                self.scenes.input(ev, true);
-                self.scenes.world.input.update_effect(ev, true);
+        if keycode == debug::TOGGLE_KEY && !repeat {
+            self.debug_overlay.toggle();
+            return;
+        }
+        if keycode == console::TOGGLE_KEY && !repeat {
+            self.console.toggle();
+            return;
+        }
+        if self.debug_overlay.visible && self.time_key(keycode, repeat) {
+            self.record(replay::Input::TimeKey { key: keycode, repeat });
+            return;
+        }
+        if keycode == screenshot::KEY && !repeat {
+            match screenshot::take(ctx, &mut self.scenes) {
+                Ok(path) => info!("Saved a screenshot to {:?}", path),
+                Err(e) => error!("Could not take a screenshot: {}", e),
             }
+            return;
+        }
+        let press = input::KeyPress {
+            keycode,
+            mods: keymod,
+            repeat,
+        };
+        if self.console.is_open() {
+            let env = console::Env::new(&self.scenes);
+            if let Some(line) = self.console.key_down(&mut self.scenes.world, &env, press) {
+                self.record(replay::Input::Command(line));
+            }
+            return;
+        }
+        if self.replaying() {
+            return;
+        }
+        self.record(replay::Input::key_down(keycode, keymod, repeat));
+        self.press_key(press);
     }
 
     fn key_up_event(
         &mut self,
         _ctx: &mut Context,
         keycode: event::KeyCode,
-        _keymod: event::KeyMods
+        keymod: event::KeyMods
     ) {
-        if let Some(ev) = self.input_binding.resolve(keycode) {
-            self.scenes.input(ev, false);
-            self.scenes.world.input.update_effect(ev, false);
+        if self.replaying() {
+            return;
         }
+        self.record(replay::Input::key_up(keycode, keymod));
+        self.release_key(keycode);
     }
 
     fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
@@ -98,15 +309,35 @@ impl event::EventHandler for MainState {
     }
 
     fn text_input_event(&mut self, _ctx: &mut ggez::Context, _character: char) {
+        if self.console.is_open() {
+            self.console.text_input(_character);
+            return;
+        }
+        if self.replaying() {
+            return;
+        }
+        self.record(replay::Input::Text(_character));
         self.scenes.text_input_event(_ctx, _character);
     }
 }
 
 fn main() {
-    util::setup_logging();
+    let args = cli::Args::parse();
+    // Settings are read first since they can say what to log, so
+    // anything wrong with them doesn't make it into the log.
+    let settings = settings::Settings::load();
+    let (log_filter, log_problems) =
+        logging::choose_filter(args.log_level.as_ref(), settings.log_filter.as_deref());
+    logging::setup(&log_filter);
+    for problem in log_problems {
+        warn!("{}", problem);
+    }
+    crash::install();
 
 
-    let resource_dir = if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
+    let resource_dir = if let Some(dir) = args.resources.clone() {
+        dir
+    } else if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
         println!("Adding path {:?}", path);
@@ -117,18 +348,35 @@ fn main() {
     println!("Resource dir: {:?}", resource_dir);
 
     let cb = ContextBuilder::new("grandpas-big-adventure", "tlboright")
-        .window_setup(conf::WindowSetup::default().title("game template"))
-        .window_mode(conf::WindowMode::default()
-                     .dimensions(800.0, 600.0)
-                     .resizable(true))
+        .window_setup(conf::WindowSetup::default()
+                      .title("game template")
+                      .vsync(settings.vsync))
+        .window_mode(args.window_mode(&settings))
         .add_resource_path(&resource_dir);
     let (ctx, ev) = &mut cb.build().unwrap();
 
-    let state = &mut MainState::new(ctx, &resource_dir);
+    let state = &mut MainState::new(ctx, &resource_dir, settings, &args);
+    if let Some(path) = args.render_to.as_ref() {
+        let rendered = screenshot::render(ctx, &mut state.scenes)
+            .and_then(|image| screenshot::save(&image, path).map_err(GameError::from));
+        match rendered {
+            Ok(()) => println!("Rendered {} to {:?}", state.scenes.current().name(), path),
+            Err(e) => {
+                println!("Could not render to {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     if let Err(e) = event::run(ctx, ev, state) {
         println!("Error encountered: {}", e);
     } else {
         println!("Game exited cleanly.");
     }
+    if let Some((recording, path)) = state.recording.as_ref() {
+        if let Err(e) = recording.write(path) {
+            error!("Could not write recording to {:?}: {}", path, e);
+        }
+    }
     
 }
@@ -0,0 +1,106 @@
+//! Entity templates, read from `/prefabs.ron`.
+//!
+//! A prefab lists the components an entity starts with and their
+//! values.  `World::spawn` makes an entity from one, with another
+//! `Prefab` of overrides on top for whatever differs at that spot,
+//! like where it is.  The file is loaded through the resource store,
+//! so edits show up in entities spawned after the next sync.
+
+use std::collections::BTreeMap;
+
+use ggez::Context;
+use serde::Deserialize;
+use specs::world::{Builder, EntityBuilder};
+
+use crate::components::{Animation, Facing, Npc, Player, Position, Sprite, Velocity};
+use crate::resources::{self, Key, Loaded, Storage};
+use crate::types::Error;
+
+pub const PREFABS_PATH: &str = "/prefabs.ron";
+
+/// Components to give an entity.  `None` means it doesn't get that
+/// one, or for overrides, that the prefab's is kept.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Prefab {
+    pub position: Option<Position>,
+    pub velocity: Option<Velocity>,
+    pub sprite: Option<Sprite>,
+    pub animation: Option<Animation>,
+    pub facing: Option<Facing>,
+    pub npc: Option<Npc>,
+    pub player: Option<Player>,
+}
+
+impl Prefab {
+    /// Overrides that just put the entity somewhere.
+    pub fn at(x: f32, y: f32) -> Self {
+        Self {
+            position: Some(Position::new(x, y)),
+            ..Self::default()
+        }
+    }
+
+    /// This prefab with anything `overrides` has replacing what's
+    /// here.
+    pub fn with_overrides(&self, overrides: &Prefab) -> Prefab {
+        Prefab {
+            position: overrides.position.or(self.position),
+            velocity: overrides.velocity.or(self.velocity),
+            sprite: overrides.sprite.clone().or_else(|| self.sprite.clone()),
+            animation: overrides.animation.clone().or_else(|| self.animation.clone()),
+            facing: overrides.facing.or(self.facing),
+            npc: overrides.npc.clone().or_else(|| self.npc.clone()),
+            player: overrides.player.or(self.player),
+        }
+    }
+
+    /// Adds the components to an entity being built.
+    pub fn build<'a>(&self, builder: EntityBuilder<'a>) -> EntityBuilder<'a> {
+        let mut builder = builder;
+        if let Some(position) = self.position {
+            builder = builder.with(position);
+        }
+        if let Some(velocity) = self.velocity {
+            builder = builder.with(velocity);
+        }
+        if let Some(sprite) = self.sprite.as_ref() {
+            builder = builder.with(sprite.clone());
+        }
+        if let Some(animation) = self.animation.as_ref() {
+            builder = builder.with(animation.clone());
+        }
+        if let Some(facing) = self.facing {
+            builder = builder.with(facing);
+        }
+        if let Some(npc) = self.npc.as_ref() {
+            builder = builder.with(npc.clone());
+        }
+        if let Some(player) = self.player {
+            builder = builder.with(player);
+        }
+        builder
+    }
+}
+
+/// Every prefab, by name.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Prefabs(BTreeMap<String, Prefab>);
+
+impl Prefabs {
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.0.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+impl warmy::Load<Context, Key> for Prefabs {
+    type Error = Error;
+    fn load(key: Key, _storage: &mut Storage, ctx: &mut Context) -> Result<Loaded<Self>, Self::Error> {
+        resources::load_ron_resource(key, ctx)
+    }
+}
@@ -0,0 +1,202 @@
+//! Draws the entities in the specs world.
+//!
+//! Everything with a `Sprite` and a `Position` is drawn in order of
+//! layer, then of how far down the screen it stands, so things in
+//! front overlap things behind in a top-down view.  Sprites sharing
+//! a texture are drawn together with a `SpriteBatch`; since the
+//! order matters, that's each run of neighbours in the sorted list
+//! rather than every sprite on a texture at once.  Characters share
+//! sheets, so runs are usually long.  Anything outside the camera's
+//! view is skipped.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use ggez::graphics::{self, spritebatch::SpriteBatch, DrawParam, Rect};
+use ggez::nalgebra::Vector2;
+use ggez::{Context, GameResult};
+use log::*;
+use specs::Join;
+
+use crate::components::{Animation, Direction, Facing, Position, PreviousPosition, Sprite};
+use crate::resources::{self, Key};
+use crate::types::Point2;
+use crate::world::World;
+
+/// What part of the world is on screen.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Camera {
+    /// The world point at the middle of the screen.
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Camera {
+    pub fn look_at(&mut self, pos: Position) {
+        self.x = pos.x;
+        self.y = pos.y;
+    }
+
+    /// The world rect that's on screen, snapped to whole pixels so
+    /// sprites don't shimmer as it moves.
+    pub fn view(&self, ctx: &Context) -> Rect {
+        let screen = graphics::screen_coordinates(ctx);
+        Rect::new(
+            (self.x - screen.w / 2.0).round(),
+            (self.y - screen.h / 2.0).round(),
+            screen.w,
+            screen.h,
+        )
+    }
+}
+
+/// One sprite, ready to go in a batch.
+struct Item {
+    layer: i32,
+    /// Where its feet are, for sorting.
+    y: f32,
+    /// Entity id, so sprites level with each other don't swap
+    /// places from frame to frame.
+    id: u32,
+    image: String,
+    param: DrawParam,
+}
+
+#[derive(Default)]
+pub struct SpriteRenderer {
+    /// Images by path; `None` for ones that wouldn't load, so the
+    /// warning only shows up once.
+    images: HashMap<String, Option<warmy::Res<resources::Image>>>,
+    batches: HashMap<String, SpriteBatch>,
+    items: Vec<Item>,
+}
+
+impl SpriteRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn image(&mut self, ctx: &mut Context, world: &mut World, path: &str) -> Option<graphics::Image> {
+        let store = &mut world.resources;
+        let res = self.images.entry(path.to_owned()).or_insert_with(|| {
+            match store.get::<resources::Image>(&Key::from_path(path), ctx) {
+                Ok(res) => Some(res),
+                Err(e) => {
+                    warn!("Could not load sprite image {:?}: {}", path, e);
+                    None
+                }
+            }
+        });
+        res.as_ref().map(|res| res.borrow().0.clone())
+    }
+
+    /// Draws every sprite in view.  `alpha` is how far between the
+    /// last update and the next to put things that are moving.
+    pub fn draw(&mut self, ctx: &mut Context, world: &mut World, camera: &Camera, alpha: f32) -> GameResult<()> {
+        let view = camera.view(ctx);
+        self.collect(ctx, world, view, alpha);
+        self.items.sort_by(|a, b| {
+            a.layer
+                .cmp(&b.layer)
+                .then(a.y.partial_cmp(&b.y).unwrap_or(Ordering::Equal))
+                .then(a.id.cmp(&b.id))
+        });
+
+        let offset = DrawParam::default().dest(Point2::new(-view.x, -view.y));
+        let mut start = 0;
+        while start < self.items.len() {
+            let image = &self.items[start].image;
+            let end = self.items[start..]
+                .iter()
+                .position(|item| &item.image != image)
+                .map_or(self.items.len(), |n| start + n);
+            // `collect` makes a batch for every image it keeps an item for.
+            if let Some(batch) = self.batches.get_mut(image) {
+                batch.clear();
+                for item in &self.items[start..end] {
+                    batch.add(item.param);
+                }
+                graphics::draw(ctx, batch, offset)?;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// Fills `items` with the sprites that overlap `view`.
+    fn collect(&mut self, ctx: &mut Context, world: &mut World, view: Rect, alpha: f32) {
+        self.items.clear();
+        let specs_world = &world.specs_world;
+        let entities = specs_world.entities();
+        let sprites = specs_world.read_storage::<Sprite>();
+        let positions = specs_world.read_storage::<Position>();
+        let previous = specs_world.read_storage::<PreviousPosition>();
+        let animations = specs_world.read_storage::<Animation>();
+        let facings = specs_world.read_storage::<Facing>();
+
+        // Pull these out first, since loading images needs the
+        // world mutably.
+        let found: Vec<(u32, Sprite, Position, usize, bool)> = (
+            &entities,
+            &sprites,
+            &positions,
+            previous.maybe(),
+            animations.maybe(),
+            facings.maybe(),
+        )
+            .join()
+            .map(|(entity, sprite, pos, prev, anim, facing)| {
+                let pos = prev.map_or(*pos, |prev| pos.lerp_from(prev, alpha));
+                let frame = anim.map_or(sprite.frame, |anim| anim.sheet_frame);
+                let flip = facing.map_or(false, |f| f.0 == Direction::Left);
+                (entity.id(), sprite.clone(), pos, frame, flip)
+            })
+            .collect();
+        drop((entities, sprites, positions, previous, animations, facings));
+
+        for (id, sprite, pos, frame, flip) in found {
+            let image = match self.image(ctx, world, &sprite.image) {
+                Some(image) => image,
+                None => continue,
+            };
+            let (image_w, image_h) = (f32::from(image.width()), f32::from(image.height()));
+            let (frame_w, frame_h) = match sprite.frame_size {
+                (w, h) if w > 0.0 && h > 0.0 => (w, h),
+                _ => (image_w, image_h),
+            };
+            let x = pos.x - sprite.origin.0 * frame_w;
+            let y = pos.y - sprite.origin.1 * frame_h;
+            if !view.overlaps(&Rect::new(x, y, frame_w, frame_h)) {
+                continue;
+            }
+
+            let columns = (image_w / frame_w).floor().max(1.0) as usize;
+            let (column, row) = (frame % columns, frame / columns);
+            let src = Rect::new(
+                column as f32 * frame_w / image_w,
+                row as f32 * frame_h / image_h,
+                frame_w / image_w,
+                frame_h / image_h,
+            );
+            self.batches
+                .entry(sprite.image.clone())
+                .or_insert_with(|| SpriteBatch::new(image));
+            self.items.push(Item {
+                layer: sprite.layer,
+                y: pos.y,
+                id,
+                image: sprite.image,
+                param: if flip {
+                    // Flipping mirrors around the left edge, so
+                    // start from the right one.
+                    DrawParam::default()
+                        .src(src)
+                        .dest(Point2::new(x + frame_w, y))
+                        .scale(Vector2::new(-1.0, 1.0))
+                } else {
+                    DrawParam::default().src(src).dest(Point2::new(x, y))
+                },
+            });
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Recording keyboard input and playing it back.
+//!
+//! A recording is every key and typed character along with the
+//! update tick it came in on, plus the random seed, so playing it
+//! back runs the game the same way again.  Console commands and the
+//! debug time keys go in too, since they change the game as much as
+//! playing it does.  Recordings are RON files written with
+//! `--record` and read with `--replay`.
+
+use std::fs;
+use std::io;
+use std::path;
+
+use ggez::event::{KeyCode, KeyMods};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    /// `mods` is `KeyMods::bits()`.
+    KeyDown { key: KeyCode, mods: u8, repeat: bool },
+    KeyUp { key: KeyCode, mods: u8 },
+    Text(char),
+    /// One of the debug overlay's keys for pausing, stepping and
+    /// slowing down game time.
+    TimeKey { key: KeyCode, repeat: bool },
+    /// A line run in the console.
+    Command(String),
+}
+
+impl Input {
+    pub fn key_down(key: KeyCode, mods: KeyMods, repeat: bool) -> Self {
+        Input::KeyDown {
+            key,
+            mods: mods.bits(),
+            repeat,
+        }
+    }
+
+    pub fn key_up(key: KeyCode, mods: KeyMods) -> Self {
+        Input::KeyUp {
+            key,
+            mods: mods.bits(),
+        }
+    }
+}
+
+/// Turns recorded modifier bits back into `KeyMods`.
+pub fn mods(bits: u8) -> KeyMods {
+    KeyMods::from_bits_truncate(bits)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    /// Update tick and what happened just before it, in order.
+    events: Vec<(u64, Input)>,
+}
+
+impl Recording {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, tick: u64, input: Input) {
+        self.events.push((tick, input));
+    }
+
+    pub fn load(path: &path::Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ron::de::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn write(&self, path: &path::Path) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        fs::write(path, text)?;
+        info!("Wrote {} recorded inputs to {:?}", self.events.len(), path);
+        Ok(())
+    }
+}
+
+/// Hands a recording's inputs back out tick by tick.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    recording: Recording,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    /// Inputs that came in before `tick`.
+    pub fn due(&mut self, tick: u64) -> Vec<Input> {
+        let events = &self.recording.events[self.next..];
+        let count = events.iter().take_while(|(t, _)| *t <= tick).count();
+        self.next += count;
+        if count > 0 && self.finished() {
+            info!("Replay finished at tick {}", tick);
+        }
+        events[..count].iter().map(|(_, input)| input.clone()).collect()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recordings_round_trip() {
+        let mut recording = Recording::new(42);
+        recording.push(0, Input::key_down(KeyCode::C, KeyMods::SHIFT, false));
+        recording.push(3, Input::TimeKey { key: KeyCode::F5, repeat: false });
+        recording.push(3, Input::Command("teleport 10 20".to_owned()));
+        recording.push(5, Input::Text('é'));
+        let text = ron::ser::to_string(&recording).unwrap();
+        let loaded: Recording = ron::de::from_str(&text).unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.events, recording.events);
+    }
+
+    #[test]
+    fn playback_hands_out_inputs_by_tick() {
+        let mut recording = Recording::new(0);
+        recording.push(0, Input::Text('a'));
+        recording.push(2, Input::Command("time pause".to_owned()));
+        recording.push(2, Input::TimeKey { key: KeyCode::F6, repeat: false });
+        let mut playback = Playback::new(recording);
+        assert_eq!(playback.due(0), vec![Input::Text('a')]);
+        assert!(playback.due(0).is_empty());
+        assert!(playback.due(1).is_empty());
+        assert_eq!(playback.due(2).len(), 2);
+        assert!(playback.finished());
+    }
+}
@@ -1,9 +1,12 @@
 //! Example of integrating ggez types with the `warmy` resource loader.
 
+use std::collections::HashSet;
+use std::fmt;
 use std::path;
 
 use ggez::{self, graphics};
 use log::*;
+use serde::de::DeserializeOwned;
 use warmy;
 
 use crate::types::Error;
@@ -39,12 +42,59 @@ impl warmy::key::Key for Key {
     }
 }
 
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
 /// Store and Storage are different things in `warmy`; the `Store`
 /// is what actually stores things, and the `Storage` is I think
 /// a handle to it.
-pub type Store = warmy::Store<ggez::Context, Key>;
-type Storage = warmy::Storage<ggez::Context, Key>;
+pub type Storage = warmy::Storage<ggez::Context, Key>;
 pub type Loaded<T> = warmy::Loaded<T, Key>;
+pub type StoreErrorOr<T> = warmy::StoreErrorOr<T, ggez::Context, Key>;
+
+/// `warmy`'s store, plus a note of everything that's been loaded
+/// through it, for the debug overlay.
+pub struct Store {
+    store: warmy::Store<ggez::Context, Key>,
+    /// A set rather than a counter so reloads don't count.
+    loaded: HashSet<Key>,
+}
+
+impl Store {
+    pub fn new(resource_dir: &path::Path) -> Result<Self, warmy::StoreError<Key>> {
+        let opt = warmy::StoreOpt::default().set_root(resource_dir);
+        Ok(Self {
+            store: warmy::Store::new(opt)?,
+            loaded: HashSet::new(),
+        })
+    }
+
+    /// Gets a resource, loading it if it isn't already.  Only ones
+    /// that load count towards `loaded_count`.
+    pub fn get<T>(&mut self, key: &Key, ctx: &mut ggez::Context) -> Result<warmy::Res<T>, StoreErrorOr<T>>
+    where
+        T: warmy::Load<ggez::Context, Key>,
+    {
+        let res = self.store.get(key, ctx)?;
+        self.loaded.insert(key.clone());
+        Ok(res)
+    }
+
+    /// Reloads anything whose file has changed.
+    pub fn sync(&mut self, ctx: &mut ggez::Context) {
+        self.store.sync(ctx);
+    }
+
+    /// How many different resources have been loaded.
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+}
 
 /// A wrapper for a ggez Image, so we can implement warmy's `Load` trait on it.
 #[derive(Debug, Clone)]
@@ -63,7 +113,64 @@ impl warmy::Load<ggez::Context, Key> for Image {
         match key {
             Key::Path(path) => graphics::Image::new(ctx, path)
                 .map(|x| warmy::Loaded::from(Image(x)))
-                .map_err(|e| Error::GgezError(e)),
+                .map_err(Error::Ggez),
         }
     }
 }
+
+/// Reads a RON file out of the ggez filesystem, for resources that
+/// are just data.
+pub fn load_ron<T>(ctx: &mut ggez::Context, path: &path::Path) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let file = ggez::filesystem::open(ctx, path).map_err(Error::Ggez)?;
+    ron::de::from_reader(file).map_err(Error::Ron)
+}
+
+/// The whole of `Load::load` for resources that are just a RON
+/// file.
+pub fn load_ron_resource<T>(key: Key, ctx: &mut ggez::Context) -> Result<Loaded<T>, Error>
+where
+    T: DeserializeOwned,
+{
+    debug!("Loading {:?}", key);
+    match key {
+        Key::Path(path) => load_ron::<T>(ctx, &path).map(warmy::Loaded::from),
+    }
+}
+
+/// Reads a RON file straight off disk, for running without ggez.
+/// `path` is the same one `load_ron` takes, rooted at `resource_dir`.
+pub fn read_ron<T, P>(resource_dir: &path::Path, path: P) -> Result<T, String>
+where
+    T: DeserializeOwned,
+    P: AsRef<path::Path>,
+{
+    let path = path.as_ref();
+    let path = resource_dir.join(path.strip_prefix("/").unwrap_or(path));
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("could not read {:?}: {}", path, e))?;
+    ron::de::from_str(&text).map_err(|e| format!("could not parse {:?}: {}", path, e))
+}
+
+/// Gets a data file the game can't do without through the store, so
+/// it reloads when the file changes.
+pub fn load_data<T>(ctx: &mut ggez::Context, store: &mut Store, path: &str) -> warmy::Res<T>
+where
+    T: warmy::Load<ggez::Context, Key, Error = Error>,
+{
+    store
+        .get::<T>(&Key::from_path(path), ctx)
+        .unwrap_or_else(|e| panic!("Could not load {}: {}", path, e))
+}
+
+/// `load_data` for running without ggez.  What it reads doesn't
+/// reload.
+#[cfg(test)]
+pub fn read_data<T>(resource_dir: &path::Path, path: &str) -> warmy::Res<T>
+where
+    T: DeserializeOwned,
+{
+    let data = read_ron(resource_dir, path).unwrap_or_else(|e| panic!("Could not load {}: {}", path, e));
+    warmy::Res::new(data)
+}
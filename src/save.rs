@@ -0,0 +1,281 @@
+//! Save games.
+//!
+//! Each slot is one RON file in the user data directory.  Every file
+//! starts with a `version` field; when loading we read that first and
+//! then parse the rest with whatever layout that version had,
+//! upgrading it to the current `SaveFile` as we go.
+//!
+//! Adding a field to `GameState` or `PlayerSave` doesn't need a new
+//! version, since they're `#[serde(default)]`.  Renaming, removing or
+//! changing the meaning of something does: bump `SAVE_VERSION`, move
+//! the old structs into a `v<N>` module with an `upgrade` that turns
+//! them into the next version's, and add an arm to `upgrade` here that
+//! chains those together.  `tests/saves` has a save of each old
+//! version to test the chain against.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path;
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Position;
+use crate::game_state::GameState;
+use crate::util;
+use crate::world::World;
+
+/// The save format version this build writes.
+pub const SAVE_VERSION: u32 = 2;
+
+/// How many save slots the slot-select screen offers.
+pub const SLOT_COUNT: usize = 3;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Parse(ron::de::Error),
+    Serialize(ron::ser::Error),
+    /// The save was written by a newer version of the game.
+    TooNew(u32),
+    /// The version is older than any the game ever wrote.
+    TooOld(u32),
+    NoPlayer,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            SaveError::Io(ref e) => write!(f, "io error: {}", e),
+            SaveError::Parse(ref e) => write!(f, "could not read save: {}", e),
+            SaveError::Serialize(ref e) => write!(f, "could not write save: {}", e),
+            SaveError::TooNew(v) => write!(
+                f,
+                "save is version {}, but this game only understands up to {}",
+                v, SAVE_VERSION
+            ),
+            SaveError::TooOld(v) => write!(f, "save is version {}, which no version of this game wrote", v),
+            SaveError::NoPlayer => write!(f, "there is no player entity to save"),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<ron::de::Error> for SaveError {
+    fn from(e: ron::de::Error) -> Self {
+        SaveError::Parse(e)
+    }
+}
+
+impl From<ron::ser::Error> for SaveError {
+    fn from(e: ron::ser::Error) -> Self {
+        SaveError::Serialize(e)
+    }
+}
+
+/// The player entity's components that are worth saving.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerSave {
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    /// RFC 3339 local time, for showing in the slot list.
+    pub saved_at: String,
+    pub game_state: GameState,
+    pub player: PlayerSave,
+}
+
+/// Just enough of a save file to find out how to read the rest.
+#[derive(Debug, Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// Version 1, from before names were limited by display width
+/// rather than grapheme count.  The layout is the same as version 2,
+/// but a name in a wide script could be up to twice as wide as the
+/// name box.
+mod v1 {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    use crate::game_state::PLAYER_NAME_MAX_CHARS;
+    use crate::util;
+
+    pub use super::SaveFile;
+
+    /// Cuts names down to what fits in the name box now.
+    pub fn upgrade(mut save: SaveFile) -> super::SaveFile {
+        if let Some(ref mut name) = save.game_state.player_name {
+            let mut width = 0;
+            let end = name
+                .grapheme_indices(true)
+                .find(|(_, g)| {
+                    width += util::grapheme_width(g);
+                    width > PLAYER_NAME_MAX_CHARS
+                })
+                .map_or(name.len(), |(i, _)| i);
+            name.truncate(end);
+        }
+        save.version = 2;
+        save
+    }
+}
+
+/// Brings a save of any version we know about up to date, one
+/// version at a time.
+fn upgrade(version: u32, text: &str) -> Result<SaveFile, SaveError> {
+    match version {
+        v if v > SAVE_VERSION => Err(SaveError::TooNew(v)),
+        SAVE_VERSION => Ok(ron::de::from_str(text)?),
+        1 => Ok(v1::upgrade(ron::de::from_str(text)?)),
+        v => Err(SaveError::TooOld(v)),
+    }
+}
+
+pub fn slot_path(slot: usize) -> path::PathBuf {
+    let mut path = util::user_data_dir();
+    path.push("saves");
+    path.push(format!("slot{}.ron", slot + 1));
+    path
+}
+
+/// Snapshots everything we save out of the world.
+pub fn capture(world: &World) -> Result<SaveFile, SaveError> {
+    let player = world.player().ok_or(SaveError::NoPlayer)?;
+    let positions = world.specs_world.read_storage::<Position>();
+    let position = positions.get(player).cloned().unwrap_or_default();
+    Ok(SaveFile {
+        version: SAVE_VERSION,
+        saved_at: chrono::Local::now().to_rfc3339(),
+        game_state: world.game_state.clone(),
+        player: PlayerSave { position },
+    })
+}
+
+/// Puts a loaded save back into the world.
+pub fn restore(world: &mut World, save: SaveFile) -> Result<(), SaveError> {
+    let player = world.player().ok_or(SaveError::NoPlayer)?;
+    world.game_state = save.game_state;
+    world
+        .specs_world
+        .write_storage::<Position>()
+        .insert(player, save.player.position)
+        .expect("Player entity vanished while restoring a save?");
+    Ok(())
+}
+
+pub fn write_slot(slot: usize, save: &SaveFile) -> Result<(), SaveError> {
+    let path = slot_path(slot);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let text = ron::ser::to_string_pretty(save, ron::ser::PrettyConfig::default())?;
+    // Write next to it and rename, so a crash mid-write doesn't
+    // eat the old save.
+    let tmp_path = path.with_extension("ron.tmp");
+    fs::write(&tmp_path, text)?;
+    fs::rename(&tmp_path, &path)?;
+    info!("Saved slot {} to {:?}", slot + 1, path);
+    Ok(())
+}
+
+/// Reads a slot.  `Ok(None)` means the slot is empty.
+pub fn read_slot(slot: usize) -> Result<Option<SaveFile>, SaveError> {
+    let path = slot_path(slot);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let header: Header = ron::de::from_str(&text)?;
+    if header.version != SAVE_VERSION {
+        info!(
+            "Upgrading slot {} from save version {} to {}",
+            slot + 1,
+            header.version,
+            SAVE_VERSION
+        );
+    }
+    upgrade(header.version, &text).map(Some)
+}
+
+/// Saves the world into a slot.
+pub fn save(world: &World, slot: usize) -> Result<(), SaveError> {
+    let save = capture(world)?;
+    write_slot(slot, &save)
+}
+
+/// Loads a slot into the world.  Returns whether there was anything
+/// in the slot.
+pub fn load(world: &mut World, slot: usize) -> Result<bool, SaveError> {
+    match read_slot(slot)? {
+        Some(save) => {
+            restore(world, save)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = include_str!("../tests/saves/v1.ron");
+
+    #[test]
+    fn version_1_saves_upgrade() {
+        let header: Header = ron::de::from_str(V1).unwrap();
+        assert_eq!(header.version, 1);
+        let save = upgrade(header.version, V1).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        // 20 CJK graphemes were fine in version 1, but are 40
+        // columns wide.
+        assert_eq!(save.game_state.player_name(), "爷爷爷爷爷爷爷爷爷爷爷爷");
+        assert_eq!(save.game_state.current_level, "garden");
+        assert_eq!(save.game_state.inventory, vec!["watering_can".to_owned()]);
+        assert_eq!(save.game_state.flags.get("met_cat"), Some(&1));
+        assert_eq!(save.player.position, Position { x: 160.0, y: 96.0 });
+    }
+
+    #[test]
+    fn names_that_fit_survive_the_upgrade() {
+        let text = V1.replace("爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷爷", "Grandpa Joe");
+        let save = upgrade(1, &text).unwrap();
+        assert_eq!(save.game_state.player_name(), "Grandpa Joe");
+    }
+
+    #[test]
+    fn only_newer_versions_are_too_new() {
+        let text = V1.replace("version: 1", &format!("version: {}", SAVE_VERSION + 1));
+        match upgrade(SAVE_VERSION + 1, &text) {
+            Err(SaveError::TooNew(v)) => assert_eq!(v, SAVE_VERSION + 1),
+            other => panic!("expected TooNew, got {:?}", other),
+        }
+        match upgrade(0, V1) {
+            Err(SaveError::TooOld(0)) => {}
+            other => panic!("expected TooOld, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn current_saves_round_trip() {
+        let mut save = upgrade(1, V1).unwrap();
+        save.game_state.play_time = 90.5;
+        let text = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default()).unwrap();
+        let loaded = upgrade(SAVE_VERSION, &text).unwrap();
+        assert_eq!(loaded.game_state.player_name(), save.game_state.player_name());
+        assert_eq!(loaded.game_state.play_time, 90.5);
+        assert_eq!(loaded.player.position, save.player.position);
+    }
+}
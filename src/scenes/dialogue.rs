@@ -0,0 +1,133 @@
+use ggez;
+use ggez::graphics::{self, DrawParam, Rect};
+use log::*;
+
+use crate::dialogue;
+use crate::host::Host;
+use crate::input;
+use crate::scenes;
+use crate::types::Point2;
+use crate::world::World;
+
+const MARGIN: f32 = 20.0;
+const BOX_HEIGHT: f32 = 150.0;
+const PADDING: f32 = 16.0;
+
+/// Someone talking, in a box along the bottom of the level.  Each
+/// line types itself out at the text speed from the settings;
+/// Select or Confirm shows the rest of it, or moves on to the next
+/// line once it's all there.  Pops itself after the last line.
+pub struct DialogueScene {
+    /// String table key for who's talking.
+    speaker: String,
+    /// String table keys for the lines.
+    lines: Vec<String>,
+    line: usize,
+    /// Characters of the current line showing so far.
+    shown: f32,
+    advance: bool,
+}
+
+impl DialogueScene {
+    pub fn new(speaker: &str, lines: &[String]) -> Self {
+        DialogueScene {
+            speaker: speaker.to_owned(),
+            lines: lines.to_vec(),
+            line: 0,
+            shown: 0.0,
+            advance: false,
+        }
+    }
+
+    fn current_line(&self, gameworld: &World) -> Option<String> {
+        let key = self.lines.get(self.line)?;
+        Some(dialogue::fill(&gameworld.strings.get(key), &gameworld.game_state))
+    }
+}
+
+impl scenes::Scene for DialogueScene {
+    fn update(&mut self, gameworld: &mut World, _ctx: &mut dyn Host) -> scenes::Switch {
+        let length = match self.current_line(gameworld) {
+            Some(line) => line.chars().count() as f32,
+            None => return scenes::Switch::None,
+        };
+        self.shown = match gameworld.settings.text_speed.chars_per_second() {
+            Some(speed) => (self.shown + speed * gameworld.settings.tick_seconds()).min(length),
+            None => length,
+        };
+
+        if self.advance {
+            self.advance = false;
+            if self.shown < length {
+                self.shown = length;
+            } else {
+                self.line += 1;
+                self.shown = 0.0;
+                if self.line >= self.lines.len() {
+                    debug!("Finished talking to {:?}", self.speaker);
+                    gameworld.request_scene(scenes::Request::Pop);
+                }
+            }
+        }
+        scenes::Switch::None
+    }
+
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let line = match self.current_line(gameworld) {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let showing: String = line.chars().take(self.shown as usize).collect();
+        let theme = gameworld.theme.borrow();
+        let font = theme.font("default");
+
+        let screen = graphics::screen_coordinates(ctx);
+        let rect = Rect::new(
+            MARGIN,
+            screen.h - BOX_HEIGHT - MARGIN,
+            screen.w - 2.0 * MARGIN,
+            BOX_HEIGHT,
+        );
+        if let Some(panel) = theme.panel("window") {
+            panel.draw(ctx, rect)?;
+        }
+
+        let speaker = font.colored(&gameworld.strings.get(&self.speaker), theme.sizes.heading, theme.palette.text);
+        let (_, speaker_h) = speaker.dimensions(ctx);
+        graphics::draw(
+            ctx,
+            &speaker,
+            DrawParam::default().dest(Point2::new(rect.x + PADDING, rect.y + PADDING)),
+        )?;
+
+        let mut text = font.colored(&showing, theme.sizes.body, theme.palette.text);
+        text.set_bounds(
+            Point2::new(rect.w - 2.0 * PADDING, f32::INFINITY),
+            graphics::Align::Left,
+        );
+        graphics::draw(
+            ctx,
+            &text,
+            DrawParam::default().dest(Point2::new(
+                rect.x + PADDING,
+                rect.y + PADDING * 1.5 + speaker_h as f32,
+            )),
+        )
+    }
+
+    fn draw_previous(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "DialogueScene"
+    }
+
+    fn input(&mut self, gameworld: &mut World, _ev: input::Event, _started: bool) {
+        if gameworld.input.get_button_pressed(input::Button::Select)
+            || gameworld.input.get_button_pressed(input::Button::Confirm)
+        {
+            self.advance = true;
+        }
+    }
+}
@@ -1,42 +1,168 @@
 use ggez;
 use ggez::graphics;
-use ggez_goodies::scene;
 use log::*;
-use specs::{self};
+use specs::{self, Join};
 use warmy;
 use ggez::nalgebra::Point2;
 
+use crate::components::{Direction, Facing, Npc, Position, PreviousPosition, Velocity};
+use crate::host::Host;
 use crate::input;
+use crate::prefab::Prefab;
+use crate::render;
 use crate::scenes;
+use crate::scenes::dialogue::DialogueScene;
+use crate::systems;
 use crate::world::World;
 
+/// Player walking speed, in pixels per second.
+const PLAYER_SPEED: f32 = 120.0;
+/// Player speed while `Button::Run` is held.
+const PLAYER_RUN_SPEED: f32 = 220.0;
+/// How close the player has to be to talk to someone, in pixels.
+const TALK_RANGE: f32 = 40.0;
+
+/// Who's in the level, and where, until levels come from map files.
+const NPCS: &[(&str, f32, f32)] = &[
+    ("neighbour", -120.0, -60.0),
+    ("postman", 160.0, 120.0),
+    ("kiwi", 60.0, -140.0),
+];
+
 pub struct LevelScene {
-    done: bool,
+    dispatcher: specs::Dispatcher<'static, 'static>,
+    paused: bool,
+    /// Whether the player asked to talk to whoever's nearby.
+    talk: bool,
+    camera: render::Camera,
+    sprites: render::SpriteRenderer,
 }
 
 impl LevelScene {
-    pub fn new(ctx: &mut ggez::Context, world: &mut World) -> Self {
-        let done = false;
+    pub fn new(_ctx: &mut dyn Host, world: &mut World) -> Self {
+        let dispatcher = systems::build_dispatcher(&mut world.specs_world);
+        // The specs world outlives the scene, so don't fill it up
+        // again when the level's rebuilt.
+        let has_npcs = (&world.specs_world.read_storage::<Npc>()).join().next().is_some();
+        if !has_npcs {
+            for &(prefab, x, y) in NPCS {
+                if world.spawn(prefab, &Prefab::at(x, y)).is_none() {
+                    warn!("No prefab {:?} for an NPC", prefab);
+                }
+            }
+        }
 
         LevelScene {
-            done,
+            dispatcher,
+            paused: false,
+            talk: false,
+            camera: render::Camera::default(),
+            sprites: render::SpriteRenderer::new(),
         }
     }
-}
 
-impl scene::Scene<World, input::Event> for LevelScene {
-    fn update(&mut self, gameworld: &mut World, ctx: &mut ggez::Context) -> scenes::Switch {
-        if self.done {
-            self.done = false;
-            scene::SceneSwitch::Push(Box::new(scenes::menu::MenuScene::new(ctx, gameworld)))
+    /// Points the player's velocity wherever the stick is pushed.
+    fn steer_player(&self, gameworld: &mut World) {
+        let horz = gameworld.input.get_axis_raw(input::Axis::Horz);
+        // Up is positive on the axis, but down on the screen.
+        let vert = -gameworld.input.get_axis_raw(input::Axis::Vert);
+        let speed = if gameworld.input.get_button_down(input::Button::Run) {
+            PLAYER_RUN_SPEED
         } else {
-            scene::SceneSwitch::None
+            PLAYER_SPEED
+        };
+        if let Some(player) = gameworld.player() {
+            let mut velocities = gameworld.specs_world.write_storage::<Velocity>();
+            if let Some(vel) = velocities.get_mut(player) {
+                vel.x = horz * speed;
+                vel.y = vert * speed;
+            }
+        }
+    }
+
+    /// Where to draw the player, between the last update and the
+    /// next.
+    fn player_position(&self, gameworld: &World, alpha: f32) -> Option<Position> {
+        let player = gameworld.player()?;
+        let positions = gameworld.specs_world.read_storage::<Position>();
+        let previous = gameworld.specs_world.read_storage::<PreviousPosition>();
+        let pos = positions.get(player)?;
+        Some(previous.get(player).map_or(*pos, |prev| pos.lerp_from(prev, alpha)))
+    }
+
+    /// Starts a conversation with the nearest NPC in range who has
+    /// something to say, turning them to face the player.
+    fn talk_to_nearest(&self, gameworld: &mut World) -> Option<scenes::BoxedScene> {
+        let player = gameworld.player()?;
+        let entities = gameworld.specs_world.entities();
+        let positions = gameworld.specs_world.read_storage::<Position>();
+        let npcs = gameworld.specs_world.read_storage::<Npc>();
+        let mut facings = gameworld.specs_world.write_storage::<Facing>();
+        let here = *positions.get(player)?;
+
+        let distance = |pos: &Position| (pos.x - here.x).hypot(pos.y - here.y);
+        let (entity, npc, pos) = (&entities, &npcs, &positions)
+            .join()
+            .filter(|(_, npc, pos)| !npc.dialogue.is_empty() && distance(pos) <= TALK_RANGE)
+            .min_by(|(_, _, a), (_, _, b)| {
+                distance(a)
+                    .partial_cmp(&distance(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })?;
+        if let Some(facing) = facings.get_mut(entity) {
+            facing.0 = Direction::of(here.x - pos.x, here.y - pos.y);
+        }
+        info!("Talking to {:?}", npc.name);
+        Some(Box::new(DialogueScene::new(&npc.name, &npc.dialogue)))
+    }
+
+    fn animation_event(&mut self, entity: specs::Entity, event: &str) {
+        match event {
+            // The game doesn't play any sound yet (menus only log
+            // theirs, as `MenuSound`s), so this is where footsteps
+            // will go once it does.
+            "footstep" => debug!("Footstep from {:?}", entity),
+            _ => debug!("Unhandled animation event {:?} from {:?}", event, entity),
         }
     }
+}
 
-    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
-        graphics::clear(ctx, graphics::BLACK);
-        Ok(())
+impl scenes::Scene for LevelScene {
+    // This only runs while the level is on top of the stack, so the
+    // pause menu freezes everything in here just by being pushed.
+    fn update(&mut self, gameworld: &mut World, ctx: &mut dyn Host) -> scenes::Switch {
+        let dt = gameworld.settings.tick_seconds();
+        self.steer_player(gameworld);
+        gameworld.specs_world.add_resource(systems::DeltaTime(dt));
+        systems::run_npcs(&gameworld.specs_world, &mut gameworld.rng);
+        self.dispatcher.dispatch(&gameworld.specs_world.res);
+        let events = systems::animate(&gameworld.specs_world, &gameworld.animations.borrow());
+        for (entity, event) in events {
+            self.animation_event(entity, &event);
+        }
+        gameworld.specs_world.maintain();
+        gameworld.game_state.play_time += dt as f64;
+
+        if self.paused {
+            self.paused = false;
+            gameworld.request_scene(scenes::Request::Push("PauseScene".to_owned()));
+        }
+        if self.talk {
+            self.talk = false;
+            if let Some(dialogue) = self.talk_to_nearest(gameworld) {
+                return scenes::Switch::Push(dialogue);
+            }
+        }
+        scenes::Switch::None
+    }
+
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, alpha: f32) -> ggez::GameResult<()> {
+        let clear_color = gameworld.theme.borrow().palette.level;
+        graphics::clear(ctx, clear_color);
+        if let Some(pos) = self.player_position(gameworld, alpha) {
+            self.camera.look_at(pos);
+        }
+        self.sprites.draw(ctx, gameworld, &self.camera, alpha)
     }
 
     fn name(&self) -> &str {
@@ -46,11 +172,14 @@ impl scene::Scene<World, input::Event> for LevelScene {
     fn input(&mut self, gameworld: &mut World, ev: input::Event, _started: bool) {
         debug!("Input: {:?}", ev);
         if gameworld.input.get_button_pressed(input::Button::Menu) {
-            self.done = true;
+            self.paused = true;
+        }
+        if gameworld.input.get_button_pressed(input::Button::Select) {
+            self.talk = true;
         }
     }
 
-    fn text_input_event(&mut self, _ctx: &mut ggez::Context, _character: char) {
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {
         println!("Text input character: {}",_character);
     }
 }
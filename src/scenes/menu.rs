@@ -1,53 +1,66 @@
 use ggez;
-use ggez::graphics::{draw, DrawParam, Font, Text};
-use ggez_goodies::scene;
+use ggez::graphics::{draw, DrawParam, Text};
 use log::*;
-use ggez::nalgebra::Point2;
 
+use crate::host::Host;
 use crate::input;
+use crate::locale::Strings;
 use crate::world::World;
 use crate::scenes;
 use crate::types::*;
+use crate::ui::Theme;
+use crate::ui::layout::{self, Align, Anchor, Stack};
 use std::collections::BTreeMap;
 
+/// Top to bottom.
+const TEXT_ORDER: &[&str] = &["title_text", "start_button"];
+
 pub struct MenuScene {
-    texts: BTreeMap<&'static str, Text>,
     done: bool,
 }
 
 impl MenuScene {
-    pub fn new(ctx: &mut ggez::Context, _world: &mut World) -> Self {
-        let font = Font::new(ctx, "/fonts/DejaVuSerif.ttf").unwrap();
-        let input_text = Text::new(("Press Any Key to Start", font, 20.0));
-        let title_text = Text::new(("Main Menu", font, 48.0));
-        let mut texts = BTreeMap::new();
-        texts.insert("start_button", input_text.clone());
-        texts.insert("title_text", title_text.clone());
-
+    pub fn new(_ctx: &mut dyn Host, _world: &mut World) -> Self {
         let done = false;
         MenuScene {
-            texts,
             done,
         }
     }
+
+    fn texts(theme: &Theme, strings: &Strings) -> BTreeMap<&'static str, Text> {
+        let font = theme.font("default");
+        let input_text = font.text(&strings.get("main_menu.start"), theme.sizes.body);
+        let title_text = font.text(&strings.get("main_menu.title"), theme.sizes.display);
+        let mut texts = BTreeMap::new();
+        texts.insert("start_button", input_text);
+        texts.insert("title_text", title_text);
+        texts
+    }
 }
 
-impl scene::Scene<World, input::Event> for MenuScene {
-    fn update(&mut self, gameworld: &mut World, ctx: &mut ggez::Context) -> scenes::Switch {
+impl scenes::Scene for MenuScene {
+    fn update(&mut self, gameworld: &mut World, ctx: &mut dyn Host) -> scenes::Switch {
         if self.done {
             self.done = false;
-            scene::SceneSwitch::Pop
+            scenes::Switch::Pop
         } else {
-            scene::SceneSwitch::None
+            scenes::Switch::None
         }
     }
 
-    fn draw(&mut self, _gameworld: &mut World, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
-        for (_key, text) in &self.texts {
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let all_texts = Self::texts(&gameworld.theme.borrow(), &gameworld.strings);
+        let texts: Vec<&Text> = TEXT_ORDER.iter().map(|key| &all_texts[key]).collect();
+        let sizes: Vec<(f32, f32)> = texts.iter().map(|t| layout::measure_text(ctx, t)).collect();
+        let rects = Stack::vertical(40.0)
+            .align(Align::Center)
+            .layout_in(&sizes, layout::screen_rect(ctx), Anchor::Center);
+
+        for (text, rect) in texts.iter().zip(rects) {
             draw(
                 ctx,
-                text,
-                DrawParam::default().dest(Point2::new(200.0,300.0)),
+                *text,
+                DrawParam::default().dest(layout::top_left(rect)),
             )?;
         }
 
@@ -64,7 +77,7 @@ impl scene::Scene<World, input::Event> for MenuScene {
         }
     }
 
-    fn text_input_event(&mut self, _ctx: &mut ggez::Context, _character: char) {
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {
         println!("Text input character: {}",_character);
     }
 }
@@ -1,15 +1,62 @@
-use ggez_goodies::scene;
 
+use crate::host::Host;
 use crate::input;
 use crate::world::World;
 
+pub mod dialogue;
 pub mod level;
 pub mod useript;
 pub mod menu;
+pub mod options;
+pub mod pause;
+pub mod registry;
+pub mod saveslot;
+pub mod stack;
 pub mod title;
+pub mod transition;
 
-// Shortcuts for our scene type.
-pub type Switch = scene::SceneSwitch<World, input::Event>;
-pub type Stack = scene::SceneStack<World, input::Event>;
-// Useless, since you can't impl type aliases.  :|
-//pub trait Scene = scene::Scene<World, input::Event>;
+pub use self::stack::Stack;
+pub use self::transition::Transition;
+
+/// One screen of the game.
+///
+/// This is `ggez_goodies::scene::Scene` with everything but `draw`
+/// taking a `Host` instead of a ggez `Context`, so scenes can run
+/// without a window (see `headless`).
+pub trait Scene {
+    fn update(&mut self, world: &mut World, ctx: &mut dyn Host) -> Switch;
+    /// `alpha` is how far the game is from the last update to the
+    /// next, from 0.0 to 1.0, for blending positions between the
+    /// two.
+    fn draw(&mut self, world: &mut World, ctx: &mut ggez::Context, alpha: f32) -> ggez::GameResult<()>;
+    fn input(&mut self, world: &mut World, event: input::Event, started: bool);
+    fn name(&self) -> &str;
+    /// Whether the scene under this one should be drawn first, for
+    /// scenes that only cover part of the screen.
+    fn draw_previous(&self) -> bool {
+        false
+    }
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {}
+}
+
+pub type BoxedScene = Box<dyn Scene>;
+
+/// A scene change returned from `Scene::update`.
+pub enum Switch {
+    None,
+    Push(BoxedScene),
+    Pop,
+}
+
+/// A scene change asked for by name rather than by handing over a
+/// built scene.  Scenes queue these with `World::request_scene` and
+/// the stack carries them out after the update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Push(String),
+    Replace(String),
+    Pop,
+    /// Pops scenes until the named one is on top.  If it isn't on
+    /// the stack at all, everything is popped and a new one pushed.
+    PopTo(String),
+}
@@ -0,0 +1,263 @@
+use ggez;
+use ggez::graphics::{draw, DrawMode, DrawParam, Mesh, Rect};
+use log::*;
+use ggez::nalgebra::Point2;
+
+use crate::host::Host;
+use crate::input;
+use crate::locale::{self, Strings};
+use crate::scenes;
+use crate::settings::{self, TextSpeed};
+use crate::ui::layout::{self, Align, Anchor, Padding, Stack};
+use crate::ui::{Menu, MenuItem, MenuStyle};
+use crate::world::World;
+
+/// How much one press of left/right moves a volume slider.
+const VOLUME_STEP: f32 = 0.1;
+
+const SLIDER_WIDTH: f32 = 200.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Row {
+    MusicVolume,
+    SfxVolume,
+    Fullscreen,
+    Vsync,
+    Resolution,
+    ShowFps,
+    TextSpeed,
+    Language,
+    Back,
+}
+
+const ROWS: &[Row] = &[
+    Row::MusicVolume,
+    Row::SfxVolume,
+    Row::Fullscreen,
+    Row::Vsync,
+    Row::Resolution,
+    Row::ShowFps,
+    Row::TextSpeed,
+    Row::Language,
+    Row::Back,
+];
+
+impl Row {
+    /// String table key for the row's label.
+    fn label(self) -> &'static str {
+        match self {
+            Row::MusicVolume => "options.music_volume",
+            Row::SfxVolume => "options.sfx_volume",
+            Row::Fullscreen => "options.fullscreen",
+            Row::Vsync => "options.vsync",
+            Row::Resolution => "options.resolution",
+            Row::ShowFps => "options.show_fps",
+            Row::TextSpeed => "options.text_speed",
+            Row::Language => "options.language",
+            Row::Back => "menu.back",
+        }
+    }
+
+    /// The volume this row's slider shows, if it's a slider.
+    fn slider_value(self, settings: &settings::Settings) -> Option<f32> {
+        match self {
+            Row::MusicVolume => Some(settings.music_volume),
+            Row::SfxVolume => Some(settings.sfx_volume),
+            _ => None,
+        }
+    }
+
+    fn value(self, settings: &settings::Settings, strings: &Strings) -> String {
+        let on_off = |b: bool| strings.get(if b { "options.on" } else { "options.off" });
+        match self {
+            Row::MusicVolume => format!("{:.0}%", settings.music_volume * 100.0),
+            Row::SfxVolume => format!("{:.0}%", settings.sfx_volume * 100.0),
+            Row::Fullscreen => on_off(settings.fullscreen),
+            Row::Vsync => on_off(settings.vsync),
+            Row::Resolution => format!("{} x {}", settings.resolution.0, settings.resolution.1),
+            Row::ShowFps => on_off(settings.show_fps),
+            Row::TextSpeed => strings.get(match settings.text_speed {
+                TextSpeed::Slow => "options.text_speed.slow",
+                TextSpeed::Normal => "options.text_speed.normal",
+                TextSpeed::Fast => "options.text_speed.fast",
+                TextSpeed::Instant => "options.text_speed.instant",
+            }),
+            Row::Language => locale::LANGUAGES
+                .iter()
+                .find(|(code, _)| *code == settings.language)
+                .map(|(_, name)| (*name).to_owned())
+                .unwrap_or_else(|| settings.language.clone()),
+            Row::Back => String::new(),
+        }
+    }
+
+    /// Nudges this row's setting one step left (`-1`) or right (`1`).
+    fn adjust(self, settings: &mut settings::Settings, dir: i32) {
+        let step = |v: f32| (v + VOLUME_STEP * dir as f32).clamp(0.0, 1.0);
+        match self {
+            Row::MusicVolume => settings.music_volume = step(settings.music_volume),
+            Row::SfxVolume => settings.sfx_volume = step(settings.sfx_volume),
+            Row::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Row::Vsync => settings.vsync = !settings.vsync,
+            Row::Resolution => {
+                let idx = settings::RESOLUTIONS
+                    .iter()
+                    .position(|r| *r == settings.resolution)
+                    .unwrap_or(0);
+                settings.resolution = settings::RESOLUTIONS[cycle(idx, dir, settings::RESOLUTIONS.len())];
+            }
+            Row::ShowFps => settings.show_fps = !settings.show_fps,
+            Row::TextSpeed => {
+                let idx = TextSpeed::ALL
+                    .iter()
+                    .position(|s| *s == settings.text_speed)
+                    .unwrap_or(0);
+                settings.text_speed = TextSpeed::ALL[cycle(idx, dir, TextSpeed::ALL.len())];
+            }
+            Row::Language => {
+                let idx = locale::LANGUAGES
+                    .iter()
+                    .position(|(code, _)| *code == settings.language)
+                    .unwrap_or(0);
+                let (code, _) = locale::LANGUAGES[cycle(idx, dir, locale::LANGUAGES.len())];
+                settings.language = code.to_owned();
+            }
+            Row::Back => (),
+        }
+    }
+}
+
+fn cycle(idx: usize, dir: i32, len: usize) -> usize {
+    (idx as i32 + dir).rem_euclid(len as i32) as usize
+}
+
+/// Saves the settings and leaves.
+fn close(world: &mut World) {
+    if let Err(e) = world.settings.write() {
+        error!("Could not save settings: {}", e);
+    }
+    world.request_scene(scenes::Request::Pop);
+}
+
+/// Where setting values go, relative to the left of the menu.
+const VALUE_COLUMN: f32 = 280.0;
+
+pub struct OptionsScene {
+    /// What the window currently reflects, so we know when the
+    /// menu has changed something that needs applying.
+    applied: settings::Settings,
+    menu: Menu,
+}
+
+impl OptionsScene {
+    pub fn new(_ctx: &mut dyn Host, world: &mut World) -> Self {
+        let mut menu = Menu::new(MenuStyle {
+            align: Align::Start,
+            spacing: 25.0,
+            ..MenuStyle::default()
+        })
+        .on_back(close);
+        for &row in ROWS {
+            let item = MenuItem::new(&world.strings.get(row.label()))
+                .on_adjust(move |world, dir| row.adjust(&mut world.settings, dir))
+                .on_select(move |world| match row {
+                    Row::Back => close(world),
+                    // Sliders only move with left and right.
+                    Row::MusicVolume | Row::SfxVolume => (),
+                    _ => row.adjust(&mut world.settings, 1),
+                });
+            menu.push(item);
+        }
+
+        OptionsScene {
+            applied: world.settings.clone(),
+            menu,
+        }
+    }
+}
+
+impl scenes::Scene for OptionsScene {
+    fn update(&mut self, gameworld: &mut World, ctx: &mut dyn Host) -> scenes::Switch {
+        if gameworld.settings.language != gameworld.strings.language() {
+            let language = gameworld.settings.language.clone();
+            gameworld.strings.set_language(ctx, &mut gameworld.resources, &language);
+        }
+        // The language is taken care of above and doesn't need the
+        // window touched.
+        self.applied.language = gameworld.settings.language.clone();
+        if gameworld.settings != self.applied {
+            // No window to apply them to when headless.
+            if let Some(ctx) = ctx.ggez() {
+                if let Err(e) = gameworld.settings.apply(ctx) {
+                    warn!("Could not apply settings: {}", e);
+                }
+            }
+            self.applied = gameworld.settings.clone();
+        }
+        scenes::Switch::None
+    }
+
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let theme = gameworld.theme.borrow();
+        let font = theme.font("default");
+        let strings = &gameworld.strings;
+        let title = font.text(&strings.get("options.title"), theme.sizes.title);
+        self.menu.set_button_style(theme.button("default"));
+        for (i, row) in ROWS.iter().enumerate() {
+            self.menu.set_label(i, &strings.get(row.label()));
+        }
+
+        let menu_size = self.menu.size(ctx);
+        let rects = Stack::vertical(40.0)
+            .padding(Padding::all(40.0))
+            .layout_in(
+                &[layout::measure_text(ctx, &title), (menu_size.0 + VALUE_COLUMN, menu_size.1)],
+                layout::screen_rect(ctx),
+                Anchor::Center,
+            );
+        draw(
+            ctx,
+            &title,
+            DrawParam::default().dest(layout::top_left(rects[0])),
+            )?;
+        let item_rects = self.menu.draw(ctx, layout::top_left(rects[1]))?;
+
+        for (i, (row, rect)) in ROWS.iter().zip(item_rects).enumerate() {
+            let color = self.menu.item_color(i);
+            let y = rect.y;
+            let mut value_x = rects[1].x + VALUE_COLUMN;
+            if let Some(volume) = row.slider_value(&gameworld.settings) {
+                let track = Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::stroke(1.0),
+                    Rect::new(value_x, y + 4.0, SLIDER_WIDTH, 14.0),
+                    color,
+                    )?;
+                let fill = Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    Rect::new(value_x, y + 4.0, SLIDER_WIDTH * volume, 14.0),
+                    color,
+                    )?;
+                draw(ctx, &track, DrawParam::default())?;
+                draw(ctx, &fill, DrawParam::default())?;
+                value_x += SLIDER_WIDTH + 15.0;
+            }
+
+            let value = font.colored(&row.value(&gameworld.settings, strings), theme.sizes.body, color);
+            draw(ctx, &value, DrawParam::default().dest(Point2::new(value_x, y)))?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "OptionsScene"
+    }
+
+    fn input(&mut self, gameworld: &mut World, _ev: input::Event, _started: bool) {
+        self.menu.handle_input(gameworld);
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {}
+}
@@ -0,0 +1,99 @@
+use ggez;
+use ggez::graphics::{self, draw, DrawMode, DrawParam, Mesh};
+use log::*;
+
+use crate::host::Host;
+use crate::input;
+use crate::scenes;
+use crate::ui::layout::{self, Align, Anchor, Stack};
+use crate::ui::{Menu, MenuItem, MenuStyle};
+use crate::world::World;
+
+/// String table keys for the menu items, top to bottom.
+const ITEMS: &[&str] = &["pause.resume", "pause.options", "pause.save", "pause.quit_to_title"];
+
+fn resume(world: &mut World) {
+    world.request_scene(scenes::Request::Pop);
+}
+
+/// The pause menu.  It sits on top of `LevelScene` in the stack,
+/// and since only the top scene gets updated, the level's systems
+/// and play time stand still until this is popped.
+pub struct PauseScene {
+    menu: Menu,
+}
+
+impl PauseScene {
+    pub fn new(_ctx: &mut dyn Host, world: &mut World) -> Self {
+        let label = |i: usize| world.strings.get(ITEMS[i]);
+        let menu = Menu::new(MenuStyle::default())
+        .item(MenuItem::new(&label(0)).on_select(resume))
+        .item(MenuItem::new(&label(1)).on_select(|world| {
+            world.request_scene(scenes::Request::Push("OptionsScene".to_owned()))
+        }))
+        .item(MenuItem::new(&label(2)).on_select(|world| {
+            world.request_scene(scenes::Request::Push("SaveGameScene".to_owned()))
+        }))
+        .item(MenuItem::new(&label(3)).on_select(|world| {
+            info!("Quitting to title");
+            world.transition(scenes::Transition::fade(graphics::BLACK, 0.6));
+            world.request_scene(scenes::Request::PopTo("TitleScene".to_owned()));
+        }))
+        .on_back(resume);
+
+        PauseScene {
+            menu,
+        }
+    }
+}
+
+impl scenes::Scene for PauseScene {
+    fn update(&mut self, _gameworld: &mut World, _ctx: &mut dyn Host) -> scenes::Switch {
+        scenes::Switch::None
+    }
+
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let theme = gameworld.theme.borrow();
+        let title = theme.font("default").text(&gameworld.strings.get("pause.title"), theme.sizes.title);
+        self.menu.set_button_style(theme.button("pause"));
+        for (i, key) in ITEMS.iter().enumerate() {
+            self.menu.set_label(i, &gameworld.strings.get(key));
+        }
+
+        // Pushes the frozen level into the background.
+        let screen = layout::screen_rect(ctx);
+        let dim = Mesh::new_rectangle(ctx, DrawMode::fill(), screen, theme.palette.dim)?;
+        draw(ctx, &dim, DrawParam::default())?;
+
+        let rects = Stack::vertical(40.0).align(Align::Center).layout_in(
+            &[layout::measure_text(ctx, &title), self.menu.size(ctx)],
+            screen,
+            Anchor::Center,
+        );
+        draw(
+            ctx,
+            &title,
+            DrawParam::default().dest(layout::top_left(rects[0])),
+            )?;
+        self.menu.draw(ctx, layout::top_left(rects[1]))?;
+
+        Ok(())
+    }
+
+    fn draw_previous(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "PauseScene"
+    }
+
+    fn input(&mut self, gameworld: &mut World, _ev: input::Event, _started: bool) {
+        self.menu.handle_input(gameworld);
+        if gameworld.input.get_button_pressed(input::Button::Menu) {
+            resume(gameworld);
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {}
+}
@@ -0,0 +1,79 @@
+//! Maps scene names to constructors, so scene changes can be asked
+//! for by name from menus, data files and the console instead of
+//! every caller having to know how to build every scene.
+
+use std::collections::BTreeMap;
+
+use crate::host::Host;
+use crate::scenes::{self, saveslot::SlotMode, BoxedScene};
+use crate::world::World;
+
+pub type Constructor = fn(&mut dyn Host, &mut World) -> BoxedScene;
+
+pub struct Registry {
+    constructors: BTreeMap<&'static str, Constructor>,
+}
+
+impl Registry {
+    /// An empty registry.  You probably want `Registry::default()`,
+    /// which knows about all our scenes.
+    pub fn new() -> Self {
+        Self {
+            constructors: BTreeMap::new(),
+        }
+    }
+
+    /// Registers a scene under `name`, which should be the same
+    /// as what the scene's `name()` returns so that `PopTo`
+    /// can find it on the stack.
+    pub fn register(&mut self, name: &'static str, constructor: Constructor) {
+        if self.constructors.insert(name, constructor).is_some() {
+            log::warn!("Scene {:?} registered twice, keeping the second", name);
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.constructors.contains_key(name)
+    }
+
+    /// Builds the named scene, or `None` if there's no such scene.
+    pub fn create(&self, name: &str, ctx: &mut dyn Host, world: &mut World) -> Option<BoxedScene> {
+        self.constructors.get(name).map(|constructor| constructor(ctx, world))
+    }
+
+    /// All registered names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.constructors.keys().cloned()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("LevelScene", |ctx, world| {
+            Box::new(scenes::level::LevelScene::new(ctx, world))
+        });
+        registry.register("MenuScene", |ctx, world| {
+            Box::new(scenes::menu::MenuScene::new(ctx, world))
+        });
+        registry.register("OptionsScene", |ctx, world| {
+            Box::new(scenes::options::OptionsScene::new(ctx, world))
+        });
+        registry.register("PauseScene", |ctx, world| {
+            Box::new(scenes::pause::PauseScene::new(ctx, world))
+        });
+        registry.register("LoadGameScene", |ctx, world| {
+            Box::new(scenes::saveslot::SaveSlotScene::new(ctx, world, SlotMode::Load))
+        });
+        registry.register("SaveGameScene", |ctx, world| {
+            Box::new(scenes::saveslot::SaveSlotScene::new(ctx, world, SlotMode::Save))
+        });
+        registry.register("TitleScene", |ctx, world| {
+            Box::new(scenes::title::TitleScene::new(ctx, world))
+        });
+        registry.register("UserInputScene", |ctx, world| {
+            Box::new(scenes::useript::UserInputScene::new(ctx, world))
+        });
+        registry
+    }
+}
@@ -0,0 +1,184 @@
+use ggez;
+use ggez::graphics::{draw, DrawParam};
+use log::*;
+
+use crate::host::Host;
+use crate::input;
+use crate::locale::Strings;
+use crate::save;
+use crate::scenes;
+use crate::ui::layout::{self, Align, Anchor, Padding, Stack};
+use crate::ui::{Menu, MenuEvent, MenuItem, MenuStyle};
+use crate::world::World;
+
+/// Whether picking a slot reads from it or writes to it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlotMode {
+    Load,
+    Save,
+}
+
+pub struct SaveSlotScene {
+    loaded: bool,
+    menu: Menu,
+    message: Option<String>,
+    mode: SlotMode,
+}
+
+impl SaveSlotScene {
+    pub fn new(_ctx: &mut dyn Host, world: &mut World, mode: SlotMode) -> Self {
+        let mut menu = Menu::new(MenuStyle {
+            align: Align::Start,
+            spacing: 40.0,
+            ..MenuStyle::default()
+        })
+        .on_back(|world| world.request_scene(scenes::Request::Pop));
+        for _ in 0..save::SLOT_COUNT {
+            menu.push(MenuItem::new(""));
+        }
+
+        let mut scene = SaveSlotScene {
+            loaded: false,
+            menu,
+            message: None,
+            mode,
+        };
+        scene.refresh_slots(&world.strings);
+        scene
+    }
+
+    /// Reads what's in each slot again.  Empty slots can't be
+    /// loaded, so they're greyed out on the load screen.
+    fn refresh_slots(&mut self, strings: &Strings) {
+        for (i, (label, filled)) in slot_labels(strings).into_iter().enumerate() {
+            self.menu.set_label(i, &label);
+            self.menu.set_enabled(i, filled || self.mode == SlotMode::Save);
+        }
+    }
+
+    fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    fn choose(&mut self, gameworld: &mut World, slot: usize) {
+        match self.mode {
+            SlotMode::Load => match save::load(gameworld, slot) {
+                Ok(true) => self.loaded = true,
+                Ok(false) => self.set_message(gameworld.strings.get("slot.empty_message")),
+                Err(e) => {
+                    error!("Could not load slot {}: {}", slot + 1, e);
+                    self.set_message(gameworld.strings.format("slot.load_failed", &[("error", &e)]));
+                }
+            },
+            SlotMode::Save => match save::save(gameworld, slot) {
+                Ok(()) => {
+                    self.refresh_slots(&gameworld.strings);
+                    self.set_message(gameworld.strings.format("slot.saved", &[("slot", &(slot + 1))]));
+                }
+                Err(e) => {
+                    error!("Could not save slot {}: {}", slot + 1, e);
+                    self.set_message(gameworld.strings.format("slot.save_failed", &[("error", &e)]));
+                }
+            },
+        }
+    }
+}
+
+/// One line per slot describing what's in it, and whether there's a
+/// save there.
+fn slot_labels(strings: &Strings) -> Vec<(String, bool)> {
+    (0..save::SLOT_COUNT)
+        .map(|slot| {
+            let number = slot + 1;
+            match save::read_slot(slot) {
+                Ok(Some(s)) => {
+                    let when = chrono::DateTime::parse_from_rfc3339(&s.saved_at)
+                        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or(s.saved_at);
+                    let minutes = (s.game_state.play_time / 60.0) as i64;
+                    let play_time = strings.plural("slot.play_time", minutes, &[]);
+                    let label = strings.format(
+                        "slot.filled",
+                        &[
+                            ("slot", &number),
+                            ("name", &s.game_state.player_name()),
+                            ("level", &s.game_state.current_level),
+                            ("when", &when),
+                            ("play_time", &play_time),
+                        ],
+                    );
+                    (label, true)
+                }
+                Ok(None) => (strings.format("slot.empty", &[("slot", &number)]), false),
+                Err(e) => {
+                    warn!("Could not read slot {}: {}", number, e);
+                    (strings.format("slot.unreadable", &[("slot", &number)]), false)
+                }
+            }
+        })
+        .collect()
+}
+
+impl scenes::Scene for SaveSlotScene {
+    fn update(&mut self, gameworld: &mut World, _ctx: &mut dyn Host) -> scenes::Switch {
+        if self.loaded {
+            self.loaded = false;
+            gameworld.request_scene(scenes::Request::Replace("LevelScene".to_owned()));
+        }
+        scenes::Switch::None
+    }
+
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let theme = gameworld.theme.borrow();
+        let font = theme.font("default");
+        let title = match self.mode {
+            SlotMode::Load => gameworld.strings.get("slot.load_title"),
+            SlotMode::Save => gameworld.strings.get("slot.save_title"),
+        };
+        let title = font.text(&title, theme.sizes.title);
+        let message = self
+            .message
+            .as_ref()
+            .map(|m| font.text(m, theme.sizes.small));
+        self.menu.set_button_style(theme.button("default"));
+
+        let mut sizes = vec![layout::measure_text(ctx, &title), self.menu.size(ctx)];
+        if let Some(ref message) = message {
+            sizes.push(layout::measure_text(ctx, message));
+        }
+        let rects = Stack::vertical(50.0)
+            .padding(Padding::symmetric(120.0, 100.0))
+            .layout_in(&sizes, layout::screen_rect(ctx), Anchor::TopLeft);
+
+        draw(
+            ctx,
+            &title,
+            DrawParam::default().dest(layout::top_left(rects[0])),
+            )?;
+        self.menu.draw(ctx, layout::top_left(rects[1]))?;
+        if let Some(ref message) = message {
+            draw(
+                ctx,
+                message,
+                DrawParam::default().dest(layout::top_left(rects[2])),
+                )?;
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        match self.mode {
+            SlotMode::Load => "LoadGameScene",
+            SlotMode::Save => "SaveGameScene",
+        }
+    }
+
+    fn input(&mut self, gameworld: &mut World, _ev: input::Event, _started: bool) {
+        if let MenuEvent::Selected(slot) = self.menu.handle_input(gameworld) {
+            self.choose(gameworld, slot);
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {}
+}
@@ -0,0 +1,260 @@
+//! Our scene stack.
+//!
+//! This works like `ggez_goodies::scene::SceneStack`, but also knows
+//! the names of the scenes on it, so scenes can be switched by name
+//! through the `Registry`, and can animate between scenes.
+
+use ggez;
+use ggez::event::KeyCode;
+use log::*;
+
+use crate::host::Host;
+use crate::input;
+use crate::scenes::{registry::Registry, transition, BoxedScene, Request, Scene, Switch};
+use crate::world::World;
+
+pub struct Stack {
+    pub world: World,
+    scenes: Vec<BoxedScene>,
+    registry: Registry,
+    transition: Option<transition::Active>,
+}
+
+impl Stack {
+    pub fn new(world: World) -> Self {
+        Self {
+            world,
+            scenes: Vec::new(),
+            registry: Registry::default(),
+            transition: None,
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn push(&mut self, scene: BoxedScene) {
+        self.scenes.push(scene)
+    }
+
+    pub fn pop(&mut self) -> BoxedScene {
+        self.scenes
+            .pop()
+            .expect("Popped an empty scene stack.")
+    }
+
+    pub fn current(&self) -> &dyn Scene {
+        &**self
+            .scenes
+            .last()
+            .expect("Tried to get current scene of an empty scene stack.")
+    }
+
+    /// Names of the scenes on the stack, bottom first.
+    pub fn names(&self) -> Vec<&str> {
+        self.scenes.iter().map(|s| s.name()).collect()
+    }
+
+    /// Whether a transition is playing.  Scenes get no input
+    /// while one is.
+    pub fn is_transitioning(&self) -> bool {
+        self.transition.is_some()
+    }
+
+    /// Builds and pushes a scene by name.
+    pub fn push_named(&mut self, ctx: &mut dyn Host, name: &str) -> bool {
+        match self.registry.create(name, ctx, &mut self.world) {
+            Some(scene) => {
+                self.push(scene);
+                true
+            }
+            None => {
+                error!("No scene named {:?} in the registry", name);
+                false
+            }
+        }
+    }
+
+    /// Carries out a `Switch` returned from a scene's `update`.
+    /// Returns the scene that was removed, if any.
+    pub fn switch(&mut self, next: Switch) -> Option<BoxedScene> {
+        match next {
+            Switch::None => None,
+            Switch::Pop => self.pop_checked(),
+            Switch::Push(s) => {
+                self.push(s);
+                None
+            }
+        }
+    }
+
+    /// Carries out a scene change asked for by name.
+    pub fn apply(&mut self, ctx: &mut dyn Host, request: Request) {
+        debug!("Scene request: {:?}", request);
+        match request {
+            Request::Push(name) => {
+                self.push_named(ctx, &name);
+            }
+            Request::Replace(name) => {
+                if self.registry.contains(&name) {
+                    self.pop();
+                    self.push_named(ctx, &name);
+                } else {
+                    error!("No scene named {:?} to replace {:?} with", name, self.current().name());
+                }
+            }
+            Request::Pop => {
+                self.pop_checked();
+            }
+            Request::PopTo(name) => {
+                if self.scenes.iter().any(|s| s.name() == name) {
+                    while self.current().name() != name {
+                        self.pop();
+                    }
+                } else if self.registry.contains(&name) {
+                    // Like when the game started from a save, right
+                    // in the level.
+                    debug!("{:?} isn't on the stack {:?}, starting over with it", name, self.names());
+                    self.scenes.clear();
+                    self.push_named(ctx, &name);
+                } else {
+                    error!("No scene named {:?} to pop to, on the stack or off it", name);
+                }
+            }
+        }
+    }
+
+    /// Pops, unless that would leave the stack empty.
+    fn pop_checked(&mut self) -> Option<BoxedScene> {
+        if self.scenes.len() > 1 {
+            Some(self.pop())
+        } else {
+            warn!("Refusing to pop the last scene, {:?}", self.current().name());
+            None
+        }
+    }
+
+    /// Updates the top scene, then carries out whatever scene
+    /// changes it (or anything else) asked for.
+    pub fn update(&mut self, ctx: &mut dyn Host) {
+        if self.is_transitioning() {
+            self.world.key_presses.clear();
+        }
+
+        let next = {
+            let current = self
+                .scenes
+                .last_mut()
+                .expect("Tried to update empty scene stack");
+            current.update(&mut self.world, ctx)
+        };
+        let requests: Vec<Request> = self.world.scene_requests.drain(..).collect();
+
+        let changing = match next {
+            Switch::None => !requests.is_empty(),
+            _ => true,
+        };
+        if changing {
+            if let Some(transition) = self.world.next_transition.take() {
+                self.start_transition(ctx, transition);
+            }
+        }
+
+        self.switch(next);
+        for request in requests {
+            self.apply(ctx, request);
+        }
+
+        let finished = match self.transition {
+            Some(ref active) => active.is_done(ctx.time_since_start()),
+            None => false,
+        };
+        if finished {
+            self.transition = None;
+        }
+        // Scenes only get one tick to look at these.
+        self.world.key_presses.clear();
+    }
+
+    /// Starts a transition, snapshotting the scenes to draw it with
+    /// if there's a window.
+    fn start_transition(&mut self, ctx: &mut dyn Host, transition: transition::Transition) {
+        let mut active = transition::Active::new(transition, ctx.time_since_start());
+        if let Some(ctx) = ctx.ggez() {
+            let clear_color = self.world.theme.borrow().palette.clear;
+            let scenes = &mut self.scenes;
+            let world = &mut self.world;
+            let snapshot = active.snapshot(ctx, clear_color, |ctx| Self::draw_scenes(scenes, world, ctx, 1.0));
+            if let Err(e) = snapshot {
+                warn!("Could not start scene transition, skipping it: {}", e);
+                return;
+            }
+        }
+        self.transition = Some(active);
+    }
+
+    /// Draws the top scene, and the ones below it for as long as
+    /// each scene's `draw_previous()` says so.  Only the top scene
+    /// updates, so only it gets `alpha`; the rest are drawn as their
+    /// last update left them.
+    pub fn draw(&mut self, ctx: &mut ggez::Context, alpha: f32) -> ggez::GameResult<()> {
+        let clear_color = self.world.theme.borrow().palette.clear;
+        let scenes = &mut self.scenes;
+        let world = &mut self.world;
+        match self.transition {
+            Some(ref mut active) => active.draw(ctx, clear_color, |ctx| Self::draw_scenes(scenes, world, ctx, alpha)),
+            None => Self::draw_scenes(scenes, world, ctx, alpha),
+        }
+    }
+
+    fn draw_scenes(
+        scenes: &mut [BoxedScene],
+        world: &mut World,
+        ctx: &mut ggez::Context,
+        alpha: f32,
+    ) -> ggez::GameResult<()> {
+        if let Some((current, rest)) = scenes.split_last_mut() {
+            if current.draw_previous() {
+                Self::draw_scenes(rest, world, ctx, 1.0)?;
+            }
+            current.draw(world, ctx, alpha)?;
+        }
+        Ok(())
+    }
+
+    /// Passes on a key press, both as the raw key and as whatever
+    /// input it's bound to.
+    pub fn key_down(&mut self, binding: &input::Binding, press: input::KeyPress) {
+        self.world.key_presses.push(press);
+        if let Some(ev) = binding.resolve(press.keycode) {
+            self.input(ev, true);
+            self.world.input.update_effect(ev, true);
+        }
+    }
+
+    pub fn key_up(&mut self, binding: &input::Binding, keycode: KeyCode) {
+        if let Some(ev) = binding.resolve(keycode) {
+            self.input(ev, false);
+            self.world.input.update_effect(ev, false);
+        }
+    }
+
+    pub fn input(&mut self, event: input::Event, started: bool) {
+        if self.is_transitioning() {
+            return;
+        }
+        if let Some(current) = self.scenes.last_mut() {
+            current.input(&mut self.world, event, started);
+        }
+    }
+
+    pub fn text_input_event(&mut self, ctx: &mut dyn Host, character: char) {
+        if self.is_transitioning() {
+            return;
+        }
+        if let Some(current) = self.scenes.last_mut() {
+            current.text_input_event(ctx, character);
+        }
+    }
+}
@@ -1,97 +1,80 @@
 use ggez;
-use ggez::graphics::{draw, Color, DrawParam, Font, Text, TextFragment};
-use ggez_goodies::scene;
+use ggez::graphics::{draw, DrawParam};
 use log::*;
-use ggez::nalgebra::Point2;
 
-use crate::types::*;
+use crate::dialogue;
+use crate::host::Host;
 use crate::input;
 use crate::world::World;
 use crate::scenes;
-use crate::types::*;
-use std::collections::BTreeMap;
-
-const BTN_ARR: &'static [&'static str; 2] = &["start_button", "options_button"];
-
-#[derive(Debug)]
-struct TextButton {
-    point: ggez::nalgebra::Point2<f32>,
-    text: Text,
+use crate::ui::layout::{self, Align, Anchor, Stack};
+use crate::ui::{Menu, MenuItem, MenuStyle};
+
+/// String table key and the scene it leads to, top to bottom.
+const ITEMS: &[(&str, &str)] = &[
+    ("title.start_game", "LevelScene"),
+    ("title.load_game", "LoadGameScene"),
+    ("title.options", "OptionsScene"),
+];
+
+/// A menu item that fades over to the named scene.
+fn scene_button(label: &str, next_scene: &'static str) -> MenuItem {
+    MenuItem::new(label).on_select(move |world| {
+        world.transition(scenes::Transition::fade(ggez::graphics::BLACK, 0.6));
+        world.request_scene(scenes::Request::Push(next_scene.to_owned()));
+    })
 }
 
 pub struct TitleScene {
-    menu_idx: usize,
-    next_scene: &'static str,
-    selected_color: Color,
-    texts: BTreeMap<&'static str, TextButton>, 
-    unselected_color: Color,
-    done: bool,
+    menu: Menu,
 }
 
 
 impl TitleScene {
-    pub fn new(ctx: &mut ggez::Context, _world: &mut World) -> Self {
-        let font = Font::new(ctx, "/fonts/DejaVuSerif.ttf").unwrap(); 
-        let mut start_game_button = Text::new(("Start Game", font, 20.0));
-        let mut options_button = Text::new(("Options", font, 20.0));
-        let mut texts = BTreeMap::new();
-        texts.insert("start_button", TextButton{text: start_game_button.clone(), point: Point2::new(200.0, 300.0)});
-        texts.insert("options_button", TextButton{text: options_button.clone(), point: Point2::new(200.0, 400.0)});
-
-        let selected_color = ggez::graphics::WHITE;
-        let unselected_color = Color::from_rgb(188, 188, 188);
+    pub fn new(_ctx: &mut dyn Host, world: &mut World) -> Self {
+        let mut menu = Menu::new(MenuStyle::default());
+        for &(key, next_scene) in ITEMS {
+            menu.push(scene_button(&world.strings.get(key), next_scene));
+        }
 
-        let mut menu_idx = 0;
-        let mut next_scene = "";
-        let done = false;
         TitleScene {
-            menu_idx,
-            next_scene,
-            selected_color,
-            texts,
-            unselected_color,
-            done,
+            menu,
         }
     }
 }
 
-impl scene::Scene<World, input::Event> for TitleScene {
-    fn update(&mut self, _gameworld: &mut World, ctx: &mut ggez::Context) -> scenes::Switch {
-        if self.done {
-            self.done = false;
-            scene::SceneSwitch::Push(Box::new(scenes::level::LevelScene::new(ctx, _gameworld)))
-        } else {
-            scene::SceneSwitch::None
-        }
+impl scenes::Scene for TitleScene {
+    fn update(&mut self, _gameworld: &mut World, _ctx: &mut dyn Host) -> scenes::Switch {
+        scenes::Switch::None
     }
 
-    fn draw(&mut self, _gameworld: &mut World, ctx: &mut ggez::Context) -> ggez::GameResult<()> {
-        let selected_key = BTN_ARR[self.menu_idx];
-        for (_key, btn) in self.texts.iter_mut() {
-            if *_key == selected_key {
-                let mut mut_frags = btn.text.fragments_mut(); 
-                for elem in mut_frags.iter_mut() {
-                   *elem = TextFragment{color: Some(self.selected_color), font: elem.font, scale: elem.scale, text: elem.text.to_string()}
-                }
-
-                draw(
-                    ctx,
-                    &btn.text,
-                    DrawParam::default().dest(btn.point),
-                    )?;
-            } else {
-                let mut mut_frags = btn.text.fragments_mut(); 
-                for elem in mut_frags.iter_mut() {
-                   *elem = TextFragment{color: Some(self.unselected_color), font: elem.font, scale: elem.scale, text: elem.text.to_string()}
-                }
-                draw(
-                    ctx,
-                    &btn.text,
-                    DrawParam::default().dest(btn.point),
-                    )?;
-            }
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> ggez::GameResult<()> {
+        let theme = gameworld.theme.borrow();
+        // Starting with `--scene TitleScene` skips asking for a name.
+        let greeting = match gameworld.game_state.player_name {
+            Some(_) => dialogue::fill(&gameworld.strings.get("title.greeting"), &gameworld.game_state),
+            None => gameworld.strings.get("title.greeting.no_name"),
+        };
+        let greeting = theme.font("default").text(&greeting, theme.sizes.heading);
+        self.menu.set_button_style(theme.button("default"));
+        for (i, &(key, _)) in ITEMS.iter().enumerate() {
+            self.menu.set_label(i, &gameworld.strings.get(key));
         }
 
+        let screen = Stack::vertical(60.0).align(Align::Center);
+        let rects = screen.layout_in(
+            &[layout::measure_text(ctx, &greeting), self.menu.size(ctx)],
+            layout::screen_rect(ctx),
+            Anchor::Center,
+        );
+
+        draw(
+            ctx,
+            &greeting,
+            DrawParam::default().dest(layout::top_left(rects[0])),
+            )?;
+        self.menu.draw(ctx, layout::top_left(rects[1]))?;
+
         Ok(())
     }
 
@@ -100,29 +83,10 @@ impl scene::Scene<World, input::Event> for TitleScene {
     }
 
     fn input(&mut self, _gameworld: &mut World, _ev: input::Event, _started: bool) {
-        let vert_axis = _gameworld.input.get_axis_raw(input::Axis::Vert);
-        
-        match _gameworld.input.get_axis_raw(input::Axis::Vert) {
-            1.0 => {
-                // self.selected_key
-                self.menu_idx = if self.menu_idx > 0 {
-                    self.menu_idx - 1
-                } else {
-                    BTN_ARR.len() - 1
-                }
-            },
-            -1.0 => { 
-                self.menu_idx = (self.menu_idx + 1) % BTN_ARR.len();
-            },
-            _ => (),
-        }
-
-        if _gameworld.input.get_button_pressed(input::Button::Menu) {
-            self.done = true;
-        }
+        self.menu.handle_input(_gameworld);
     } 
 
-    fn text_input_event(&mut self, _ctx: &mut ggez::Context, _character: char) {
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, _character: char) {
         println!("Text input character: {}",_character);
     }
 }
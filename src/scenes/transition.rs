@@ -0,0 +1,182 @@
+//! Animated transitions between scenes.
+//!
+//! A scene that wants its next scene change animated calls
+//! `World::transition()` before returning the switch (or queueing the
+//! request).  The stack then snapshots what was on screen, makes the
+//! change, and spends the transition's duration blending from the
+//! snapshot to the new scene, which keeps updating and drawing live
+//! underneath.  Input isn't passed to scenes until it's done.
+
+use std::time::Duration;
+
+use ggez::graphics::{self, Canvas, Color, DrawMode, DrawParam, Mesh, Rect};
+use ggez::{timer, Context, GameResult};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Effect {
+    /// Fades out to a solid color, then in from it.
+    Fade(Color),
+    /// Blends the old scene straight into the new one.
+    CrossFade,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transition {
+    pub effect: Effect,
+    /// Seconds.
+    pub duration: f32,
+}
+
+impl Transition {
+    pub fn new(effect: Effect, duration: f32) -> Self {
+        Self { effect, duration }
+    }
+
+    pub fn fade(color: Color, duration: f32) -> Self {
+        Self::new(Effect::Fade(color), duration)
+    }
+
+    pub fn cross_fade(duration: f32) -> Self {
+        Self::new(Effect::CrossFade, duration)
+    }
+}
+
+/// A transition that's underway.  Its timing runs on the host's
+/// clock, so it holds up input the same with or without a window.
+pub struct Active {
+    transition: Transition,
+    started: Duration,
+    /// `None` when there's no window to draw in.
+    canvases: Option<Canvases>,
+}
+
+struct Canvases {
+    /// What was on screen just before the switch.
+    outgoing: Canvas,
+    /// The new scene gets drawn into this each frame.
+    incoming: Canvas,
+}
+
+impl Active {
+    /// Starts a transition at `now` (time since the game started).
+    /// Call `snapshot` before the switch to have something to draw.
+    pub fn new(transition: Transition, now: Duration) -> Self {
+        Self {
+            transition,
+            started: now,
+            canvases: None,
+        }
+    }
+
+    /// Keeps what's on screen to transition away from.
+    /// `draw_outgoing` should draw the scene stack as it is right
+    /// before the switch.
+    pub fn snapshot<F>(&mut self, ctx: &mut Context, clear_color: Color, draw_outgoing: F) -> GameResult<()>
+    where
+        F: FnOnce(&mut Context) -> GameResult<()>,
+    {
+        let outgoing = Canvas::with_window_size(ctx)?;
+        let incoming = Canvas::with_window_size(ctx)?;
+        graphics::set_canvas(ctx, Some(&outgoing));
+        graphics::clear(ctx, clear_color);
+        let result = draw_outgoing(ctx);
+        graphics::set_canvas(ctx, None);
+        result?;
+
+        self.canvases = Some(Canvases { outgoing, incoming });
+        Ok(())
+    }
+
+    /// How far along we are at `now` (time since the game
+    /// started), from 0.0 to 1.0.
+    pub fn progress(&self, now: Duration) -> f32 {
+        if self.transition.duration <= 0.0 {
+            return 1.0;
+        }
+        let elapsed = timer::duration_to_f64(now.checked_sub(self.started).unwrap_or_default()) as f32;
+        (elapsed / self.transition.duration).min(1.0)
+    }
+
+    pub fn is_done(&self, now: Duration) -> bool {
+        self.progress(now) >= 1.0
+    }
+
+    /// Draws the transition to the screen.  `draw_incoming` should
+    /// draw the scene stack as it is now.  Without a snapshot that's
+    /// all that gets drawn.
+    pub fn draw<F>(&mut self, ctx: &mut Context, clear_color: Color, draw_incoming: F) -> GameResult<()>
+    where
+        F: FnOnce(&mut Context) -> GameResult<()>,
+    {
+        let canvases = match self.canvases {
+            Some(ref canvases) => canvases,
+            None => return draw_incoming(ctx),
+        };
+        graphics::set_canvas(ctx, Some(&canvases.incoming));
+        graphics::clear(ctx, clear_color);
+        let result = draw_incoming(ctx);
+        graphics::set_canvas(ctx, None);
+        result?;
+
+        let t = self.progress(timer::time_since_start(ctx));
+        let (w, h) = graphics::drawable_size(ctx);
+        let screen = Rect::new(0.0, 0.0, w, h);
+        match self.transition.effect {
+            Effect::Fade(color) => {
+                let (canvas, amount) = if t < 0.5 {
+                    (&canvases.outgoing, t * 2.0)
+                } else {
+                    (&canvases.incoming, (1.0 - t) * 2.0)
+                };
+                graphics::draw(ctx, canvas, DrawParam::default())?;
+                let cover = Mesh::new_rectangle(ctx, DrawMode::fill(), screen, with_alpha(color, amount))?;
+                graphics::draw(ctx, &cover, DrawParam::default())?;
+            }
+            Effect::CrossFade => {
+                graphics::draw(ctx, &canvases.outgoing, DrawParam::default())?;
+                graphics::draw(
+                    ctx,
+                    &canvases.incoming,
+                    DrawParam::default().color(with_alpha(graphics::WHITE, t)),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn with_alpha(color: Color, alpha: f32) -> Color {
+    Color { a: color.a * alpha, ..color }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn progress_runs_from_zero_to_one_over_the_duration() {
+        let active = Active::new(Transition::cross_fade(0.5), secs(10.0));
+        assert_eq!(active.progress(secs(10.0)), 0.0);
+        assert!((active.progress(secs(10.25)) - 0.5).abs() < 1e-6);
+        assert!(!active.is_done(secs(10.4)));
+        assert_eq!(active.progress(secs(10.5)), 1.0);
+        assert!(active.is_done(secs(10.5)));
+        assert_eq!(active.progress(secs(60.0)), 1.0);
+    }
+
+    #[test]
+    fn a_clock_from_before_the_start_counts_as_the_start() {
+        let active = Active::new(Transition::fade(graphics::BLACK, 1.0), secs(10.0));
+        assert_eq!(active.progress(secs(9.0)), 0.0);
+    }
+
+    #[test]
+    fn zero_length_transitions_are_done_straight_away() {
+        let active = Active::new(Transition::cross_fade(0.0), secs(10.0));
+        assert!(active.is_done(secs(10.0)));
+    }
+}
@@ -1,26 +1,19 @@
 use ggez::{self, GameResult};
-use ggez::event::{KeyCode};
-use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Text, TextFragment};
-use ggez_goodies::scene;
+use ggez::graphics::{self, DrawParam};
 use log::*;
 use specs::{self};
 use warmy;
 
+use crate::game_state;
+use crate::host::Host;
 use crate::input;
+use crate::locale::Strings;
 use crate::scenes;
 use crate::types::Point2;
+use crate::ui;
 use crate::world::World;
 
-pub const TEXT_COLOR: Color = Color {
-    r: 1.0,
-    g: 1.0,
-    b: 1.0,
-    a: 1.0,
-};
-
-const INPUT_MAX_CHAR: usize = 25;
-
-pub const TEXT_PROMPT: &str = "What is your name?";
+const INPUT_MAX_CHAR: usize = game_state::PLAYER_NAME_MAX_CHARS;
 
 struct RectDim {
     x: f32,
@@ -35,87 +28,70 @@ impl RectDim {
     }
 }
 
+fn error_text(error: game_state::NameError, strings: &Strings) -> String {
+    match error {
+        game_state::NameError::Empty => strings.get("name.error.empty"),
+        game_state::NameError::TooLong => {
+            strings.format("name.error.too_long", &[("max", &game_state::PLAYER_NAME_MAX_CHARS)])
+        }
+    }
+}
+
 pub struct UserInputScene {
-    background_color: Color,
     done: bool,
-    font: Font,
-    input_box_color: Color,
-    input_text: String,
-    text_input_rendered: bool,
-    window_color: Color,
+    error: Option<game_state::NameError>,
+    input_text: ui::TextField,
 }
 
 impl UserInputScene {
-    pub fn new(ctx: &mut ggez::Context, world: &mut World) -> Self {
-        let BLACK_COFFEE = Color::from_rgb(41, 31, 30);
-        let CELADON_BLUE = Color::from_rgb(71, 121, 152);
+    pub fn new(_ctx: &mut dyn Host, _world: &mut World) -> Self {
         let done = false;
-        let font = Font::new(ctx, "/fonts/DejaVuSerif.ttf").unwrap(); 
 
-        let text_input_rendered = false;
-        let mut input_text = "".to_string();
-        let background_color = BLACK_COFFEE;
-        let input_box_color = Color::from((50, 50, 50, 255));
-        let window_color = CELADON_BLUE;
+        let input_text = ui::TextField::new(INPUT_MAX_CHAR);
 
         UserInputScene {
-            background_color,
             done,
-            font,
-            input_box_color,
+            error: None,
             input_text,
-            text_input_rendered,
-            window_color,
         }
     }
 }
 
-impl scene::Scene<World, input::Event> for UserInputScene {
-    fn update(&mut self, gameworld: &mut World, ctx: &mut ggez::Context) -> scenes::Switch {
+impl scenes::Scene for UserInputScene {
+    fn update(&mut self, gameworld: &mut World, _ctx: &mut dyn Host) -> scenes::Switch {
+        for key in gameworld.key_presses.iter() {
+            self.input_text.handle_key(*key);
+        }
+
         if self.done {
             self.done = false;
-            scene::SceneSwitch::Push(Box::new(scenes::menu::MenuScene::new(ctx, gameworld)))
-        } else {
-            scene::SceneSwitch::None
+            gameworld.transition(scenes::Transition::cross_fade(0.5));
+            gameworld.request_scene(scenes::Request::Replace("TitleScene".to_owned()));
         }
+        scenes::Switch::None
     }
 
-    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context) -> GameResult<()> {
+    fn draw(&mut self, gameworld: &mut World, ctx: &mut ggez::Context, _alpha: f32) -> GameResult<()> {
+        let theme = gameworld.theme.borrow();
+        let font = theme.font("default");
         let (drawable_width, drawable_height) = graphics::drawable_size(ctx);
-        graphics::clear(ctx, self.background_color);
+        graphics::clear(ctx, theme.palette.background);
         let rd = RectDim::new(drawable_width / 4.0, drawable_height / 3.3, drawable_width / 2.0, drawable_height / 3.3);
 
-        let rect = graphics::Mesh::new_rectangle(
-            ctx,
-            graphics::DrawMode::fill(),
-            graphics::Rect::new(rd.x, rd.y, rd.w, rd.h),
-            self.window_color,
-            )?;
+        if let Some(panel) = theme.panel("window") {
+            panel.draw(ctx, graphics::Rect::new(rd.x, rd.y, rd.w, rd.h))?;
+        }
 
+        let input_box = graphics::Rect::new(rd.x + 30.0, rd.y + (rd.h - 60.0), rd.w - 60.0, 30.0);
         let input_rect = graphics::Mesh::new_rectangle(
             ctx,
             graphics::DrawMode::fill(),
-            graphics::Rect::new(rd.x + 30.0, rd.y + (rd.h - 60.0), rd.w - 60.0, 30.0),
-            self.input_box_color,
+            input_box,
+            theme.palette.input_box,
             )?;
 
-        let prompt_text = Text::new(TextFragment {
-            text: (TEXT_PROMPT).to_string(),
-            color: Some(TEXT_COLOR),
-            font: Some(self.font),
-            scale: Some(graphics::Scale::uniform(19.0)),
-            ..Default::default()
-        });
-
-        let text = Text::new(TextFragment {
-            text: (self.input_text).to_string(),
-            color: Some(TEXT_COLOR),
-            font: Some(self.font),
-            scale: Some(graphics::Scale::uniform(21.0)),
-            ..Default::default()
-        });
-
-        graphics::draw(ctx, &rect, (Point2::new(0.0, 0.0),))?;
+        let prompt_text = font.colored(&gameworld.strings.get("name.prompt"), theme.sizes.body, theme.palette.text);
+
         graphics::draw(ctx, &input_rect, (Point2::new(0.0, 0.0),))?;
 
         graphics::draw(
@@ -124,22 +100,41 @@ impl scene::Scene<World, input::Event> for UserInputScene {
             DrawParam::default().dest(Point2::new(rd.x + (rd.w / 4.0), rd.y + (rd.h - 128.0)),),
             )?;
 
-        graphics::draw(
-            ctx,
-            &text,
-            DrawParam::default().dest(Point2::new(rd.x + 38.0, rd.y + (rd.h - 54.0)),),
-            )?;
+        let style = ui::TextFieldStyle {
+            font: font.clone(),
+            scale: theme.sizes.body,
+            text_color: theme.palette.text,
+            caret_color: theme.palette.text,
+            selection_color: theme.palette.selection,
+            padding: 8.0,
+        };
+        self.input_text.draw(ctx, input_box, &style)?;
+
+        if let Some(error) = self.error {
+            let error_text = font.colored(&error_text(error, &gameworld.strings), theme.sizes.small, theme.palette.error);
+            graphics::draw(
+                ctx,
+                &error_text,
+                DrawParam::default().dest(Point2::new(rd.x + 30.0, rd.y + rd.h + 10.0),),
+                )?;
+        }
         Ok(())
     }
     
-    fn input(&mut self, gameworld: &mut World, ev: input::Event, _started: bool) {
-        if gameworld.input.get_button_pressed(input::Button::Delete) {
-            let mut next_str = self.input_text.to_owned();
-            next_str.pop();
-            self.input_text = next_str;
-        }
+    fn input(&mut self, gameworld: &mut World, _ev: input::Event, _started: bool) {
+        // Backspace and friends are handled by the text field in `update`.
         if gameworld.input.get_button_pressed(input::Button::Confirm) {
-            println!("enter pushed");
+            match gameworld.game_state.set_player_name(self.input_text.text()) {
+                Ok(()) => {
+                    info!("Player name set to {:?}", gameworld.game_state.player_name());
+                    self.error = None;
+                    self.done = true;
+                }
+                Err(e) => {
+                    debug!("Rejected player name {:?}: {}", self.input_text.text(), e);
+                    self.error = Some(e);
+                }
+            }
         }
     }
 
@@ -147,17 +142,7 @@ impl scene::Scene<World, input::Event> for UserInputScene {
         "UserInputScene"
     }
 
-    fn text_input_event(&mut self, ctx: &mut ggez::Context, _character: char) {
-        println!("delete is pressed: {}", ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Back));
-        match ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Back) || ggez::input::keyboard::is_key_pressed(ctx, KeyCode::Return){
-            true => (),
-            false => {
-                let mut next_str = self.input_text.to_owned();
-                if next_str.chars().count() < INPUT_MAX_CHAR {
-                    next_str.push_str(&_character.to_string());
-                    self.input_text = next_str;
-                }
-            }
-        }
+    fn text_input_event(&mut self, _ctx: &mut dyn Host, character: char) {
+        self.input_text.insert_char(character);
     }
 }
@@ -0,0 +1,210 @@
+//! Screenshots, and the golden-image tests built on them.
+//!
+//! The scene stack is drawn to an offscreen canvas the size of the
+//! window rather than read back from the screen, so the debug
+//! overlay and console aren't in the picture.  F12 saves one to
+//! `screenshots` in the user data directory; `--render-to` saves
+//! the starting scene and quits.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Canvas, Color};
+use ggez::{Context, GameError, GameResult};
+use image::RgbaImage;
+
+use crate::scenes;
+use crate::util;
+
+pub const KEY: KeyCode = KeyCode::F12;
+
+/// Draws the scene stack as it would be on screen.
+pub fn render(ctx: &mut Context, scenes: &mut scenes::Stack) -> GameResult<RgbaImage> {
+    let canvas = Canvas::with_window_size(ctx)?;
+    graphics::set_canvas(ctx, Some(&canvas));
+    // The window has no alpha channel, so the screen is opaque
+    // whatever the theme's clear color says.
+    let clear_color = Color {
+        a: 1.0,
+        ..scenes.world.theme.borrow().palette.clear
+    };
+    graphics::clear(ctx, clear_color);
+    let drawn = scenes.draw(ctx, 1.0);
+    // Put the screen back even if drawing went wrong.
+    graphics::set_canvas(ctx, None);
+    drawn?;
+    // ggez only sends draws to the GPU when it presents a frame, and
+    // reading the canvas back goes around them, so send them now.
+    let (_, device, encoder, _, _) = graphics::gfx_objects(ctx);
+    encoder.flush(device);
+
+    let image = canvas.image();
+    let (width, height) = (u32::from(image.width()), u32::from(image.height()));
+    let pixels = image.to_rgba8(ctx)?;
+    let upside_down = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| GameError::RenderError("Canvas was the wrong size for its pixels".to_owned()))?;
+    // OpenGL reads back the bottom row first.
+    Ok(image::imageops::flip_vertical(&upside_down))
+}
+
+pub fn save(image: &RgbaImage, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    image.save(path)
+}
+
+/// Renders the scene stack into a new, dated file in the
+/// screenshots directory, and says where it went.
+pub fn take(ctx: &mut Context, scenes: &mut scenes::Stack) -> GameResult<PathBuf> {
+    let image = render(ctx, scenes)?;
+    let mut path = util::user_data_dir();
+    path.push("screenshots");
+    path.push(format!("screenshot-{}.png", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    save(&image, &path)?;
+    Ok(path)
+}
+
+/// Golden-image tests.  These need a real window and graphics
+/// driver, so they're ignored by default; run them with `make golden`
+/// (`cargo test scenes_match -- --ignored`).  A missing golden image
+/// is a failure.  `make golden-update` (`UPDATE_GOLDEN=1`) writes new
+/// ones after a layout change you meant to make; look at what's in
+/// `tests/golden` before committing it.
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use ggez::{conf, ContextBuilder};
+
+    use super::*;
+    use crate::settings::Settings;
+    use crate::world::World;
+
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 600.0;
+    /// Per-channel slack, for differences between graphics drivers.
+    const THRESHOLD: u8 = 8;
+    /// Fraction of pixels allowed past `THRESHOLD`, for text
+    /// antialiasing and the blinking caret.
+    const TOLERANCE: f32 = 0.01;
+    const SCENES: &[&str] = &["TitleScene", "MenuScene", "UserInputScene"];
+
+    /// How much of `actual` is different from `expected`: the fraction
+    /// of pixels where some channel is off by more than `threshold`.
+    /// Images of different sizes are completely different.
+    fn difference(expected: &RgbaImage, actual: &RgbaImage, threshold: u8) -> f32 {
+        if expected.dimensions() != actual.dimensions() {
+            return 1.0;
+        }
+        let total = expected.pixels().len();
+        if total == 0 {
+            return 0.0;
+        }
+        let differing = expected
+            .pixels()
+            .zip(actual.pixels())
+            .filter(|(a, b)| {
+                a.0.iter()
+                    .zip(b.0.iter())
+                    .any(|(x, y)| (i16::from(*x) - i16::from(*y)).abs() > i16::from(threshold))
+            })
+            .count();
+        differing as f32 / total as f32
+    }
+
+    fn manifest_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    #[ignore]
+    fn scenes_match_golden_images() {
+        let resource_dir = manifest_dir().join("resources");
+        let golden_dir = manifest_dir().join("tests").join("golden");
+        let update = env::var_os("UPDATE_GOLDEN").is_some();
+
+        let (ctx, _events) = &mut ContextBuilder::new("grandpas-big-adventure-tests", "tlboright")
+            .window_mode(conf::WindowMode::default().dimensions(WIDTH, HEIGHT))
+            .add_resource_path(&resource_dir)
+            .build()
+            .expect("Could not open a window");
+
+        let mut failures = Vec::new();
+        for name in SCENES {
+            let mut world = World::new(ctx, &resource_dir, Settings::default(), 0);
+            world.game_state.set_player_name("Grandpa").unwrap();
+            let mut stack = scenes::Stack::new(world);
+            assert!(stack.push_named(ctx, name), "no scene called {}", name);
+            let actual = render(ctx, &mut stack).expect("Could not render");
+
+            let golden = golden_dir.join(format!("{}.png", name));
+            if update {
+                save(&actual, &golden).expect("Could not write golden image");
+                continue;
+            }
+
+            let problem = match image::open(&golden) {
+                Ok(expected) => {
+                    let diff = difference(&expected.to_rgba(), &actual, THRESHOLD);
+                    if diff <= TOLERANCE {
+                        continue;
+                    }
+                    format!("{:.2}% of pixels differ", diff * 100.0)
+                }
+                Err(e) => format!("could not read {:?}: {}", golden, e),
+            };
+            let actual_path = env::temp_dir().join(format!("{}-actual.png", name));
+            save(&actual, &actual_path).expect("Could not write render");
+            failures.push(format!("{}: {}, render is at {:?}", name, problem, actual_path));
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    fn filled(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, image::Rgba(pixel))
+    }
+
+    #[test]
+    fn identical_images_dont_differ() {
+        let image = filled(4, 4, [10, 20, 30, 255]);
+        assert_eq!(difference(&image, &image.clone(), 0), 0.0);
+    }
+
+    #[test]
+    fn differences_up_to_the_threshold_dont_count() {
+        let expected = filled(2, 2, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, image::Rgba([100 + THRESHOLD, 100, 100 - THRESHOLD, 255]));
+        assert_eq!(difference(&expected, &actual, THRESHOLD), 0.0);
+
+        actual.put_pixel(1, 1, image::Rgba([100, 100, 100, 255 - THRESHOLD - 1]));
+        assert_eq!(difference(&expected, &actual, THRESHOLD), 0.25);
+    }
+
+    #[test]
+    fn a_blinking_caret_is_within_tolerance() {
+        let expected = filled(WIDTH as u32, HEIGHT as u32, [0, 0, 0, 255]);
+        let mut actual = expected.clone();
+        for y in 0..20 {
+            for x in 0..2 {
+                actual.put_pixel(100 + x, 100 + y, image::Rgba([255, 255, 255, 255]));
+            }
+        }
+        let diff = difference(&expected, &actual, THRESHOLD);
+        assert!(diff > 0.0 && diff <= TOLERANCE, "{}", diff);
+
+        let half = filled(WIDTH as u32, HEIGHT as u32 / 2, [255, 255, 255, 255]);
+        image::imageops::replace(&mut actual, &half, 0, 0);
+        assert!(difference(&expected, &actual, THRESHOLD) > TOLERANCE);
+    }
+
+    #[test]
+    fn images_of_different_sizes_are_completely_different() {
+        let expected = filled(4, 4, [0, 0, 0, 255]);
+        assert_eq!(difference(&expected, &filled(4, 5, [0, 0, 0, 255]), 255), 1.0);
+        assert_eq!(difference(&expected, &filled(0, 0, [0, 0, 0, 255]), 255), 1.0);
+    }
+}
@@ -0,0 +1,163 @@
+//! Player-adjustable settings, kept in `settings.ron` in the user
+//! data directory.
+
+use std::fs;
+use std::io;
+use std::path;
+
+use ggez::{conf, graphics, Context, GameResult};
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{locale, util};
+
+/// Window sizes offered on the options screen.
+pub const RESOLUTIONS: &[(u32, u32)] = &[
+    (800, 600),
+    (1024, 768),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+];
+
+/// How fast dialogue text types itself out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextSpeed {
+    Slow,
+    Normal,
+    Fast,
+    Instant,
+}
+
+impl TextSpeed {
+    pub const ALL: [TextSpeed; 4] = [
+        TextSpeed::Slow,
+        TextSpeed::Normal,
+        TextSpeed::Fast,
+        TextSpeed::Instant,
+    ];
+
+    /// Characters revealed per second; `None` means all at once.
+    pub fn chars_per_second(self) -> Option<f32> {
+        match self {
+            TextSpeed::Slow => Some(20.0),
+            TextSpeed::Normal => Some(40.0),
+            TextSpeed::Fast => Some(80.0),
+            TextSpeed::Instant => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// 0.0 to 1.0.
+    pub music_volume: f32,
+    /// 0.0 to 1.0.
+    pub sfx_volume: f32,
+    pub fullscreen: bool,
+    /// ggez can only set this when creating the window, so changes
+    /// take effect on the next launch.
+    pub vsync: bool,
+    /// Window size when not fullscreen.
+    pub resolution: (u32, u32),
+    pub show_fps: bool,
+    pub text_speed: TextSpeed,
+    /// Code of the language the UI is shown in, like `"en"`.
+    pub language: String,
+    /// What to log; see `logging` for the format.  `None` uses
+    /// `logging::DEFAULT_FILTER`.
+    pub log_filter: Option<String>,
+    /// Game logic updates per second.
+    pub tick_rate: u32,
+    /// Most updates to run in one frame when the game falls behind,
+    /// before it gives up and slows down instead.
+    pub max_catch_up_ticks: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 0.8,
+            sfx_volume: 0.8,
+            fullscreen: false,
+            vsync: true,
+            resolution: RESOLUTIONS[0],
+            show_fps: true,
+            text_speed: TextSpeed::Normal,
+            language: locale::FALLBACK_LANGUAGE.to_owned(),
+            log_filter: None,
+            tick_rate: util::DESIRED_FPS,
+            max_catch_up_ticks: 5,
+        }
+    }
+}
+
+impl Settings {
+    /// Seconds of game time in one update.
+    pub fn tick_seconds(&self) -> f32 {
+        1.0 / self.tick_rate.max(1) as f32
+    }
+
+    pub fn path() -> path::PathBuf {
+        let mut path = util::user_data_dir();
+        path.push("settings.ron");
+        path
+    }
+
+    /// Reads the settings file, falling back to defaults if it's
+    /// missing or broken.
+    pub fn load() -> Self {
+        let path = Self::path();
+        match fs::read_to_string(&path) {
+            Ok(text) => ron::de::from_str(&text).unwrap_or_else(|e| {
+                warn!("Could not parse {:?}, using default settings: {}", path, e);
+                Self::default()
+            }),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                warn!("Could not read {:?}, using default settings: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        fs::write(&path, text)?;
+        info!("Wrote settings to {:?}", path);
+        Ok(())
+    }
+
+    pub fn window_mode(&self) -> conf::WindowMode {
+        let (w, h) = self.resolution;
+        conf::WindowMode::default()
+            .dimensions(w as f32, h as f32)
+            .fullscreen_type(self.fullscreen_type())
+            .resizable(true)
+    }
+
+    fn fullscreen_type(&self) -> conf::FullscreenType {
+        if self.fullscreen {
+            conf::FullscreenType::Desktop
+        } else {
+            conf::FullscreenType::Windowed
+        }
+    }
+
+    /// Pushes whatever can change at runtime to the window.
+    pub fn apply(&self, ctx: &mut Context) -> GameResult<()> {
+        graphics::set_fullscreen(ctx, self.fullscreen_type())?;
+        if !self.fullscreen {
+            let (w, h) = self.resolution;
+            graphics::set_drawable_size(ctx, w as f32, h as f32)?;
+            graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, w as f32, h as f32))?;
+        }
+        Ok(())
+    }
+}
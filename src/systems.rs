@@ -0,0 +1,329 @@
+//! Systems that run on the specs world during gameplay.
+
+use std::f32::consts::PI;
+
+use log::*;
+use rand::{rngs::StdRng, Rng};
+use specs::{self, Entities, Entity, Join, Read, ReadStorage, RunNow, System, WriteStorage};
+
+use crate::animation::AnimationSets;
+use crate::components::{
+    Animation, Behaviour, Direction, Facing, Npc, Player, Position, PreviousPosition, Velocity,
+};
+
+/// Seconds per update tick.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct DeltaTime(pub f32);
+
+/// Notes where everything is before anything moves.
+pub struct RememberPositions;
+
+impl<'a> System<'a> for RememberPositions {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, PreviousPosition>,
+    );
+
+    fn run(&mut self, (entities, positions, mut previous): Self::SystemData) {
+        for (entity, pos) in (&entities, &positions).join() {
+            let remembered = PreviousPosition { x: pos.x, y: pos.y };
+            if let Some(prev) = previous.get_mut(entity) {
+                *prev = remembered;
+            } else if let Err(e) = previous.insert(entity, remembered) {
+                warn!("Could not remember where {:?} was: {}", entity, e);
+            }
+        }
+    }
+}
+
+/// Moves everything with a velocity.
+pub struct Movement;
+
+impl<'a> System<'a> for Movement {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Velocity>,
+        WriteStorage<'a, Position>,
+    );
+
+    fn run(&mut self, (dt, velocities, mut positions): Self::SystemData) {
+        for (vel, pos) in (&velocities, &mut positions).join() {
+            pos.x += vel.x * dt.0;
+            pos.y += vel.y * dt.0;
+        }
+    }
+}
+
+/// Turns things to face the way they're moving.
+pub struct FaceMovement;
+
+impl<'a> System<'a> for FaceMovement {
+    type SystemData = (ReadStorage<'a, Velocity>, WriteStorage<'a, Facing>);
+
+    fn run(&mut self, (velocities, mut facings): Self::SystemData) {
+        for (vel, facing) in (&velocities, &mut facings).join() {
+            if vel.x.hypot(vel.y) >= WALK_SPEED {
+                facing.0 = Direction::of(vel.x, vel.y);
+            }
+        }
+    }
+}
+
+/// How close an NPC has to get to where it's going to count as
+/// there, in pixels.
+const ARRIVED: f32 = 2.0;
+/// Seconds a wandering NPC stands still between strolls.
+const WANDER_PAUSE: (f32, f32) = (1.0, 4.0);
+
+/// Decides where NPCs are going and sets their velocities.  Takes
+/// the world's random number generator, so wandering comes out the
+/// same for the same seed.
+pub struct NpcBehaviour<'r> {
+    pub rng: &'r mut StdRng,
+}
+
+impl<'a, 'r> System<'a> for NpcBehaviour<'r> {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Npc>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, Facing>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (dt, players, positions, mut npcs, mut velocities, mut facings) = data;
+        let player = (&players, &positions).join().map(|(_, pos)| *pos).next();
+        let moving = (&mut npcs, &positions, &mut velocities, (&mut facings).maybe());
+        for (npc, pos, vel, facing) in moving.join() {
+            let home = *npc.home.get_or_insert(*pos);
+            if let Some(player) = player {
+                let (dx, dy) = (player.x - pos.x, player.y - pos.y);
+                if dx.hypot(dy) < npc.notice_radius {
+                    *vel = Velocity::default();
+                    if let Some(facing) = facing {
+                        facing.0 = Direction::of(dx, dy);
+                    }
+                    continue;
+                }
+            }
+
+            let target = match npc.behaviour.clone() {
+                Behaviour::Idle => None,
+                Behaviour::Wander { radius } => self.wander(npc, home, radius, dt.0),
+                Behaviour::FollowPath { points, looping } => follow_path(npc, &points, looping),
+            };
+            *vel = match target {
+                Some(target) => {
+                    let (dx, dy) = (target.x - pos.x, target.y - pos.y);
+                    let distance = dx.hypot(dy);
+                    if distance <= ARRIVED {
+                        Velocity::default()
+                    } else {
+                        // Don't overshoot on the last step.
+                        let speed = npc.speed.min(distance / dt.0.max(f32::EPSILON));
+                        Velocity {
+                            x: dx / distance * speed,
+                            y: dy / distance * speed,
+                        }
+                    }
+                }
+                None => Velocity::default(),
+            };
+            if let Some(target) = target {
+                if (target.x - pos.x).hypot(target.y - pos.y) <= ARRIVED {
+                    npc.target = None;
+                    if let Behaviour::Wander { .. } = npc.behaviour {
+                        npc.wait = self.rng.gen_range(WANDER_PAUSE.0, WANDER_PAUSE.1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'r> NpcBehaviour<'r> {
+    /// Where a wandering NPC is headed, picking somewhere new once
+    /// it's done waiting.
+    fn wander(&mut self, npc: &mut Npc, home: Position, radius: f32, dt: f32) -> Option<Position> {
+        if npc.wait > 0.0 {
+            npc.wait -= dt;
+            return None;
+        }
+        if npc.target.is_none() {
+            let angle = self.rng.gen_range(0.0, 2.0 * PI);
+            // The square root spreads spots evenly over the circle
+            // instead of bunching them up in the middle.
+            let distance = radius * self.rng.gen::<f32>().sqrt();
+            npc.target = Some(Position::new(
+                home.x + angle.cos() * distance,
+                home.y + angle.sin() * distance,
+            ));
+        }
+        npc.target
+    }
+}
+
+/// Where an NPC on a path is headed.  Moves on to the next point
+/// once it's at the current one.
+fn follow_path(npc: &mut Npc, points: &[(f32, f32)], looping: bool) -> Option<Position> {
+    if points.is_empty() {
+        return None;
+    }
+    if npc.target.is_none() {
+        if npc.path_index >= points.len() {
+            npc.path_index = 0;
+        }
+        let (x, y) = points[npc.path_index];
+        npc.target = Some(Position::new(x, y));
+        npc.path_index = next_path_index(npc, points.len(), looping);
+    }
+    npc.target
+}
+
+fn next_path_index(npc: &mut Npc, len: usize, looping: bool) -> usize {
+    let last = len - 1;
+    if last == 0 {
+        return 0;
+    }
+    if looping {
+        return (npc.path_index + 1) % len;
+    }
+    if npc.path_backwards && npc.path_index == 0 {
+        npc.path_backwards = false;
+    } else if !npc.path_backwards && npc.path_index >= last {
+        npc.path_backwards = true;
+    }
+    if npc.path_backwards {
+        npc.path_index - 1
+    } else {
+        npc.path_index + 1
+    }
+}
+
+/// Runs NPC behaviour.  Like `animate`, this can't go in the
+/// dispatcher, since the random number generator lives on the game
+/// world; run it before the dispatcher so NPCs move the same update.
+pub fn run_npcs(specs_world: &specs::World, rng: &mut StdRng) {
+    NpcBehaviour { rng }.run_now(&specs_world.res);
+}
+
+/// Below this speed, in pixels per second, things animate as
+/// standing still.
+pub const WALK_SPEED: f32 = 1.0;
+/// At or above this speed things animate as running.
+pub const RUN_SPEED: f32 = 180.0;
+
+/// Picks the idle, walk or run clip by speed, for animations that
+/// want that.  Sets without a run clip walk instead, and ones
+/// without a walk clip stay idle.
+pub struct ChooseClips<'s> {
+    pub sets: &'s AnimationSets,
+}
+
+impl<'a, 's> System<'a> for ChooseClips<'s> {
+    type SystemData = (ReadStorage<'a, Velocity>, WriteStorage<'a, Animation>);
+
+    fn run(&mut self, (velocities, mut animations): Self::SystemData) {
+        for (vel, anim) in (&velocities, &mut animations).join() {
+            if !anim.by_speed {
+                continue;
+            }
+            let speed = vel.x.hypot(vel.y);
+            let wanted: &[&str] = if speed < WALK_SPEED {
+                &["idle"]
+            } else if speed < RUN_SPEED {
+                &["walk", "idle"]
+            } else {
+                &["run", "walk", "idle"]
+            };
+            if let Some(clip) = wanted.iter().find(|c| self.sets.has_clip(&anim.set, c)) {
+                anim.play(clip);
+            }
+        }
+    }
+}
+
+/// Moves animations along, and collects the events of the frames
+/// they come to.
+pub struct Animate<'s> {
+    pub sets: &'s AnimationSets,
+    pub events: Vec<(Entity, String)>,
+}
+
+impl<'a, 's> System<'a> for Animate<'s> {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, Animation>,
+    );
+
+    fn run(&mut self, (entities, dt, mut animations): Self::SystemData) {
+        let events = &mut self.events;
+        for (entity, anim) in (&entities, &mut animations).join() {
+            match self.sets.clip(&anim.set, &anim.clip) {
+                Some(clip) => clip.advance(anim, dt.0, |event| events.push((entity, event.to_owned()))),
+                None => trace!("{:?} has no animation {:?} in {:?}", entity, anim.clip, anim.set),
+            }
+        }
+    }
+}
+
+/// Runs the animation systems and returns the events that fired.
+/// These can't go in the dispatcher, since the animation sets live
+/// in the resource store and can't be shared between threads.
+pub fn animate(specs_world: &specs::World, sets: &AnimationSets) -> Vec<(Entity, String)> {
+    ChooseClips { sets }.run_now(&specs_world.res);
+    let mut animate = Animate {
+        sets,
+        events: Vec::new(),
+    };
+    animate.run_now(&specs_world.res);
+    animate.events
+}
+
+/// The dispatcher `LevelScene` runs every update.
+pub fn build_dispatcher(specs_world: &mut specs::World) -> specs::Dispatcher<'static, 'static> {
+    let mut dispatcher = specs::DispatcherBuilder::new()
+        .with(RememberPositions, "remember_positions", &[])
+        .with(Movement, "movement", &["remember_positions"])
+        .with(FaceMovement, "face_movement", &[])
+        .build();
+    dispatcher.setup(&mut specs_world.res);
+    dispatcher
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The path points an NPC heads for, starting from the first.
+    fn path(len: usize, looping: bool, steps: usize) -> Vec<usize> {
+        let mut npc = Npc::default();
+        let mut indices = vec![npc.path_index];
+        for _ in 0..steps {
+            npc.path_index = next_path_index(&mut npc, len, looping);
+            indices.push(npc.path_index);
+        }
+        indices
+    }
+
+    #[test]
+    fn looping_paths_start_over() {
+        assert_eq!(path(4, true, 9), vec![0, 1, 2, 3, 0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn other_paths_go_back_and_forth() {
+        assert_eq!(path(4, false, 9), vec![0, 1, 2, 3, 2, 1, 0, 1, 2, 3]);
+        assert_eq!(path(2, false, 4), vec![0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn one_point_paths_stay_put() {
+        assert_eq!(path(1, true, 3), vec![0, 0, 0, 0]);
+        assert_eq!(path(1, false, 3), vec![0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Runs game logic at a fixed rate, however fast frames are drawn.
+//!
+//! Frame time goes into an accumulator, and a tick comes out for
+//! every whole tick's worth in there.  What's left over is how far
+//! the game is between the last tick and the next, which drawing
+//! uses to blend positions so motion doesn't stutter when the frame
+//! rate and tick rate don't line up.
+//!
+//! For debugging, `TimeControl` can slow game time down or stop it
+//! and let it through a tick at a time.
+
+use std::time::Duration;
+
+use log::*;
+
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    tick: Duration,
+    /// Most ticks to run in one frame.  Past that the game slows
+    /// down instead of trying to catch up, since if ticks take
+    /// longer than they cover, catching up only makes it fall
+    /// further behind.
+    max_ticks: u32,
+    accumulator: Duration,
+    /// Whether the last frame was paused, so drawing shows exactly
+    /// what the last tick left instead of blending.
+    paused: bool,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: u32, max_ticks: u32) -> Self {
+        Self {
+            tick: Duration::from_secs(1) / tick_rate.max(1),
+            max_ticks: max_ticks.max(1),
+            accumulator: Duration::default(),
+            paused: false,
+        }
+    }
+
+    /// Adds a frame's worth of time, sped up or slowed down by
+    /// `time`, and says how many ticks to run for it.
+    pub fn advance(&mut self, frame_time: Duration, time: &mut TimeControl) -> u32 {
+        self.paused = time.paused;
+        if time.paused {
+            self.accumulator = Duration::default();
+            // Big steps run over several frames, like slow ones do.
+            let ticks = time.steps.min(self.max_ticks);
+            time.steps -= ticks;
+            return ticks;
+        }
+        // Not `clamp`, which passes NaN through for `mul_f32` to
+        // panic on.
+        #[allow(clippy::manual_clamp)]
+        let scale = time.scale.max(0.0).min(TimeControl::MAX_SCALE);
+        self.accumulator += frame_time.mul_f32(scale);
+        let mut ticks = 0;
+        while self.accumulator >= self.tick && ticks < self.max_ticks {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+        if self.accumulator >= self.tick {
+            debug!(
+                "Fell {:?} behind, dropping it rather than running more than {} ticks",
+                self.accumulator, self.max_ticks
+            );
+            // Keep the part of a tick we were partway through, so
+            // alpha stays smooth.
+            self.accumulator = Duration::from_nanos(
+                (self.accumulator.as_nanos() % self.tick.as_nanos()) as u64,
+            );
+        }
+        ticks
+    }
+
+    /// How far from the last tick to the next we are, from 0.0 to
+    /// just under 1.0.
+    pub fn alpha(&self) -> f32 {
+        if self.paused {
+            return 1.0;
+        }
+        let alpha = self.accumulator.as_nanos() as f64 / self.tick.as_nanos() as f64;
+        // Just short of a tick can round up to a whole one as an f32.
+        (alpha as f32).min(1.0 - f32::EPSILON / 2.0)
+    }
+}
+
+/// How fast game time runs, for looking at things closely.
+#[derive(Debug, Clone)]
+pub struct TimeControl {
+    /// Seconds of game time per second of real time, from 0.0 to
+    /// `MAX_SCALE`.  Set it with `set_scale`.
+    pub scale: f32,
+    /// Whether ticks only run when stepped.
+    pub paused: bool,
+    /// Ticks asked for with `step` that haven't run yet.
+    steps: u32,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            steps: 0,
+        }
+    }
+}
+
+impl TimeControl {
+    /// The scales the slow motion key goes through.
+    pub const SLOW_MOTION: [f32; 4] = [1.0, 0.5, 0.25, 0.1];
+    /// Most ticks that can be waiting to be stepped through.
+    pub const MAX_STEPS: u32 = 60 * 60;
+    /// Fastest game time can run.  Much faster and every frame hits
+    /// `FixedTimestep`'s tick limit anyway.
+    pub const MAX_SCALE: f32 = 16.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how fast game time runs, kept between 0.0 and
+    /// `MAX_SCALE`.  Returns the scale it ended up at.
+    pub fn set_scale(&mut self, scale: f32) -> f32 {
+        self.scale = if scale.is_nan() { 1.0 } else { scale.clamp(0.0, Self::MAX_SCALE) };
+        self.scale
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.steps = 0;
+    }
+
+    /// Pauses if need be, and runs `ticks` more ticks, up to
+    /// `MAX_STEPS` waiting.  Returns how many it added.
+    pub fn step(&mut self, ticks: u32) -> u32 {
+        self.paused = true;
+        let added = ticks.min(Self::MAX_STEPS - self.steps);
+        self.steps += added;
+        added
+    }
+
+    /// Goes to the next slower scale, back around to full speed.
+    pub fn cycle_slow_motion(&mut self) {
+        let next = Self::SLOW_MOTION
+            .iter()
+            .position(|&s| s < self.scale)
+            .unwrap_or(0);
+        self.scale = Self::SLOW_MOTION[next];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_are_kept_in_range() {
+        let mut time = TimeControl::new();
+        assert_eq!(time.set_scale(2.0), 2.0);
+        assert_eq!(time.set_scale(1e30), TimeControl::MAX_SCALE);
+        assert_eq!(time.set_scale(f32::INFINITY), TimeControl::MAX_SCALE);
+        assert_eq!(time.set_scale(-1.0), 0.0);
+        assert_eq!(time.set_scale(f32::NAN), 1.0);
+    }
+
+    #[test]
+    fn frames_run_the_ticks_they_cover() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        let tick = Duration::from_secs(1) / 60;
+        assert_eq!(timestep.advance(tick / 2, &mut time), 0);
+        assert_eq!(timestep.advance(tick / 2, &mut time), 1);
+        assert_eq!(timestep.advance(tick * 3, &mut time), 3);
+        // A 144Hz monitor runs a tick about every other frame, and
+        // they add up to the right number.
+        let ticks: u32 = (0..144)
+            .map(|_| timestep.advance(Duration::from_secs(1) / 144, &mut time))
+            .sum();
+        assert!((59..=61).contains(&ticks), "{} ticks", ticks);
+    }
+
+    #[test]
+    fn slow_frames_run_at_most_max_ticks() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        let tick = Duration::from_secs(1) / 60;
+        assert_eq!(timestep.advance(Duration::from_secs(2), &mut time), 5);
+        // What's left over is dropped rather than run next frame.
+        assert_eq!(timestep.advance(Duration::default(), &mut time), 0);
+        let partway = tick * 5 + tick / 2;
+        assert_eq!(timestep.advance(partway, &mut time), 5);
+        assert!((timestep.alpha() - 0.5).abs() < 0.01, "{}", timestep.alpha());
+    }
+
+    #[test]
+    fn alpha_is_how_far_into_the_next_tick_we_are() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        assert_eq!(timestep.alpha(), 0.0);
+        // Frames of all sorts of odd lengths up to 50ms.
+        for i in 0..2000u64 {
+            let frame = Duration::from_nanos(i * 7_919_113 % 50_000_000);
+            timestep.advance(frame, &mut time);
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha {} after {:?}", alpha, frame);
+        }
+        // Just short of a tick still isn't a whole one.
+        let mut timestep = FixedTimestep::new(60, 5);
+        timestep.advance(Duration::from_secs(1) / 60 - Duration::from_nanos(1), &mut time);
+        assert!(timestep.alpha() < 1.0);
+    }
+
+    #[test]
+    fn paused_time_only_runs_steps() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        time.toggle_pause();
+        assert_eq!(timestep.advance(Duration::from_secs(1), &mut time), 0);
+        assert_eq!(timestep.alpha(), 1.0);
+        time.step(2);
+        assert_eq!(timestep.advance(Duration::from_secs(1), &mut time), 2);
+        assert_eq!(timestep.advance(Duration::from_secs(1), &mut time), 0);
+    }
+
+    #[test]
+    fn big_steps_run_at_most_max_ticks_a_frame() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        assert_eq!(time.step(12), 12);
+        assert_eq!(timestep.advance(Duration::default(), &mut time), 5);
+        assert_eq!(timestep.advance(Duration::default(), &mut time), 5);
+        assert_eq!(timestep.advance(Duration::default(), &mut time), 2);
+        assert_eq!(timestep.advance(Duration::default(), &mut time), 0);
+        assert_eq!(time.step(u32::MAX), TimeControl::MAX_STEPS);
+        assert_eq!(time.step(1), 0);
+    }
+
+    #[test]
+    fn a_huge_scale_doesnt_overflow() {
+        let mut timestep = FixedTimestep::new(60, 5);
+        let mut time = TimeControl::new();
+        time.scale = f32::INFINITY;
+        assert_eq!(timestep.advance(Duration::from_secs(1), &mut time), 5);
+        time.scale = f32::NAN;
+        assert_eq!(timestep.advance(Duration::from_secs(1), &mut time), 0);
+    }
+}
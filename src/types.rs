@@ -5,13 +5,18 @@ pub use ggez::nalgebra::Point2;
 /// but is still useful.
 #[derive(Debug)]
 pub enum Error {
-    GgezError(ggez::GameError),
+    Ggez(ggez::GameError),
+    Ron(ron::de::Error),
+    /// A theme file that parsed but doesn't make sense.
+    Theme(String),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
-            Error::GgezError(ref e) => write!(f, "ggez error: {}", e),
+            Error::Ggez(ref e) => write!(f, "ggez error: {}", e),
+            Error::Ron(ref e) => write!(f, "RON error: {}", e),
+            Error::Theme(ref e) => write!(f, "theme error: {}", e),
         }
     }
 }
@@ -0,0 +1,135 @@
+//! Fonts that fall back to other fonts glyph by glyph.
+//!
+//! No one font has every script in it, so a `FontStack` is a list
+//! of fonts tried in order: each grapheme is drawn in the first font
+//! that has glyphs for it.  This is what lets names typed in
+//! Japanese or with emoji show up instead of empty boxes.
+
+use std::fmt;
+use std::io::Read;
+
+use ggez::graphics::{Color, Font, Scale, Text, TextFragment};
+use ggez::Context;
+use log::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::types::Error;
+
+#[derive(Clone)]
+struct StackFont {
+    font: Font,
+    /// The same font again, parsed so we can ask what's in it.
+    glyphs: rusttype::Font<'static>,
+}
+
+impl StackFont {
+    fn load(ctx: &mut Context, path: &str) -> Result<Self, Error> {
+        let mut bytes = Vec::new();
+        ggez::filesystem::open(ctx, path)
+            .map_err(Error::Ggez)?
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::Ggez(e.into()))?;
+        let font = Font::new_glyph_font_bytes(ctx, &bytes).map_err(Error::Ggez)?;
+        let glyphs = rusttype::Font::from_bytes(bytes)
+            .map_err(|e| Error::Theme(format!("could not read font {:?}: {}", path, e)))?;
+        Ok(Self { font, glyphs })
+    }
+}
+
+fn has_glyph(font: &rusttype::Font, c: char) -> bool {
+    font.glyph(c).id().0 != 0
+}
+
+/// Which of `fonts` to draw `grapheme` in: the first that can draw
+/// all of it, or failing that its first char.
+pub fn pick<'a, 'f, I>(fonts: I, grapheme: &str) -> Option<usize>
+where
+    I: Iterator<Item = &'a rusttype::Font<'f>> + Clone,
+    'f: 'a,
+{
+    let first = grapheme.chars().next()?;
+    fonts
+        .clone()
+        .position(|f| grapheme.chars().all(|c| has_glyph(f, c)))
+        .or_else(|| fonts.clone().position(|f| has_glyph(f, first)))
+}
+
+#[derive(Clone, Default)]
+pub struct FontStack {
+    /// Empty for the default stack, which is just ggez's built-in
+    /// font and so needs nothing loaded.
+    fonts: Vec<StackFont>,
+}
+
+impl fmt::Debug for FontStack {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FontStack")
+            .field("fonts", &self.fonts.iter().map(|f| f.font).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl FontStack {
+    /// Loads the fonts at `paths`.  The first one has to load; the
+    /// rest are nice to have and are skipped with a warning if
+    /// they're missing.
+    pub fn load(ctx: &mut Context, paths: &[String]) -> Result<Self, Error> {
+        let (first, rest) = paths
+            .split_first()
+            .ok_or_else(|| Error::Theme("a font needs at least one file".to_owned()))?;
+        let mut fonts = vec![StackFont::load(ctx, first)?];
+        for path in rest {
+            match StackFont::load(ctx, path) {
+                Ok(font) => fonts.push(font),
+                Err(e) => warn!("Skipping fallback font {:?}: {}", path, e),
+            }
+        }
+        Ok(Self { fonts })
+    }
+
+    /// The font most text is drawn in.
+    pub fn primary(&self) -> Font {
+        self.fonts.first().map_or_else(Font::default, |f| f.font)
+    }
+
+    /// The first font that can draw the whole grapheme, or failing
+    /// that its first char, or failing that the primary font.
+    fn font_for(&self, grapheme: &str) -> Font {
+        let glyphs = self.fonts.iter().map(|f| &f.glyphs);
+        pick(glyphs, grapheme).map_or_else(|| self.primary(), |i| self.fonts[i].font)
+    }
+
+    /// Splits `s` into runs that each use one font.
+    pub fn runs<'a>(&self, s: &'a str) -> Vec<(Font, &'a str)> {
+        let mut runs: Vec<(Font, usize, usize)> = Vec::new();
+        for (i, grapheme) in s.grapheme_indices(true) {
+            let font = self.font_for(grapheme);
+            let end = i + grapheme.len();
+            match runs.last_mut() {
+                Some(run) if run.0 == font => run.2 = end,
+                _ => runs.push((font, i, end)),
+            }
+        }
+        runs.into_iter().map(|(font, start, end)| (font, &s[start..end])).collect()
+    }
+
+    pub fn text(&self, s: &str, scale: f32) -> Text {
+        self.build(s, scale, None)
+    }
+
+    pub fn colored(&self, s: &str, scale: f32, color: Color) -> Text {
+        self.build(s, scale, Some(color))
+    }
+
+    fn build(&self, s: &str, scale: f32, color: Option<Color>) -> Text {
+        let mut text = Text::default();
+        for (font, run) in self.runs(s) {
+            let mut fragment = TextFragment::new(run).font(font).scale(Scale::uniform(scale));
+            if let Some(color) = color {
+                fragment = fragment.color(color);
+            }
+            text.add(fragment);
+        }
+        text
+    }
+}
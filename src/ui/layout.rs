@@ -0,0 +1,217 @@
+//! A very small layout helper, so screens can be described as
+//! "these things stacked up, centered" instead of pixel positions.
+//!
+//! It's immediate-mode: measure your items, hand their sizes to a
+//! `Stack`, and get back a rect for each one to draw into.  Stacks
+//! nest by using one stack's `size()` as an item in another.
+
+use ggez::graphics::{self, Rect, Text};
+use ggez::Context;
+
+use crate::types::Point2;
+
+/// Where items sit across a stack: at its left edge or centered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+}
+
+impl Align {
+    /// Offset of something `size` long inside a space `room` long.
+    fn offset(self, size: f32, room: f32) -> f32 {
+        match self {
+            Align::Start => 0.0,
+            Align::Center => (room - size) / 2.0,
+        }
+    }
+}
+
+/// Which point of a container something is pinned to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Center,
+}
+
+impl Anchor {
+    /// Top-left corner for something of `size` anchored inside `within`.
+    pub fn place(self, size: (f32, f32), within: Rect) -> Point2<f32> {
+        let (h_align, v_align) = match self {
+            Anchor::TopLeft => (Align::Start, Align::Start),
+            Anchor::Center => (Align::Center, Align::Center),
+        };
+        Point2::new(
+            within.x + h_align.offset(size.0, within.w),
+            within.y + v_align.offset(size.1, within.h),
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Padding {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Padding {
+    pub fn all(p: f32) -> Self {
+        Self {
+            top: p,
+            right: p,
+            bottom: p,
+            left: p,
+        }
+    }
+
+    pub fn symmetric(horizontal: f32, vertical: f32) -> Self {
+        Self {
+            top: vertical,
+            right: horizontal,
+            bottom: vertical,
+            left: horizontal,
+        }
+    }
+
+    /// Shrinks a rect by this padding.
+    pub fn shrink(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x + self.left,
+            rect.y + self.top,
+            (rect.w - self.left - self.right).max(0.0),
+            (rect.h - self.top - self.bottom).max(0.0),
+        )
+    }
+}
+
+/// Items one above the other.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stack {
+    /// Gap between neighbouring items.
+    pub spacing: f32,
+    pub align: Align,
+    pub padding: Padding,
+}
+
+impl Stack {
+    pub fn vertical(spacing: f32) -> Self {
+        Self {
+            spacing,
+            align: Align::Start,
+            padding: Padding::default(),
+        }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Total size of the stack, padding included.
+    pub fn size(&self, sizes: &[(f32, f32)]) -> (f32, f32) {
+        let gaps = self.spacing * sizes.len().saturating_sub(1) as f32;
+        let w = sizes.iter().map(|s| s.0).fold(0.0, f32::max);
+        let h = sizes.iter().map(|s| s.1).sum::<f32>() + gaps;
+        (
+            w + self.padding.left + self.padding.right,
+            h + self.padding.top + self.padding.bottom,
+        )
+    }
+
+    /// Lays the items out with the stack's top-left corner at `origin`.
+    pub fn layout(&self, sizes: &[(f32, f32)], origin: Point2<f32>) -> Vec<Rect> {
+        let (total_w, total_h) = self.size(sizes);
+        let inner = self.padding.shrink(Rect::new(origin.x, origin.y, total_w, total_h));
+        let mut cursor = 0.0;
+        sizes
+            .iter()
+            .map(|&(w, h)| {
+                let rect = Rect::new(inner.x + self.align.offset(w, inner.w), inner.y + cursor, w, h);
+                cursor += h + self.spacing;
+                rect
+            })
+            .collect()
+    }
+
+    /// Lays the items out with the whole stack anchored inside `within`.
+    pub fn layout_in(&self, sizes: &[(f32, f32)], within: Rect, anchor: Anchor) -> Vec<Rect> {
+        let origin = anchor.place(self.size(sizes), within);
+        self.layout(sizes, origin)
+    }
+}
+
+/// How big a piece of text will be when drawn.
+pub fn measure_text(ctx: &mut Context, text: &Text) -> (f32, f32) {
+    let (w, h) = text.dimensions(ctx);
+    (w as f32, h as f32)
+}
+
+/// The whole drawable area, in screen coordinates.
+pub fn screen_rect(ctx: &Context) -> Rect {
+    graphics::screen_coordinates(ctx)
+}
+
+/// Where a rect's top-left corner is, for `DrawParam::dest`.
+pub fn top_left(rect: Rect) -> Point2<f32> {
+    Point2::new(rect.x, rect.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: &[(f32, f32)] = &[(100.0, 20.0), (60.0, 30.0), (80.0, 10.0)];
+
+    #[test]
+    fn stacks_are_as_wide_as_their_widest_item() {
+        let stack = Stack::vertical(5.0);
+        assert_eq!(stack.size(SIZES), (100.0, 70.0));
+        assert_eq!(stack.size(&[]), (0.0, 0.0));
+        let padded = stack.padding(Padding::symmetric(10.0, 4.0));
+        assert_eq!(padded.size(SIZES), (120.0, 78.0));
+    }
+
+    #[test]
+    fn items_go_down_with_spacing_between() {
+        let stack = Stack::vertical(5.0).padding(Padding::all(2.0));
+        let rects = stack.layout(SIZES, Point2::new(10.0, 100.0));
+        assert_eq!(
+            rects,
+            vec![
+                Rect::new(12.0, 102.0, 100.0, 20.0),
+                Rect::new(12.0, 127.0, 60.0, 30.0),
+                Rect::new(12.0, 162.0, 80.0, 10.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn centered_items_line_up_on_the_middle() {
+        let rects = Stack::vertical(0.0).align(Align::Center).layout(SIZES, Point2::new(0.0, 0.0));
+        let middles: Vec<f32> = rects.iter().map(|r| r.x + r.w / 2.0).collect();
+        assert_eq!(middles, vec![50.0, 50.0, 50.0]);
+    }
+
+    #[test]
+    fn anchors_pin_to_the_corner_or_middle() {
+        let screen = Rect::new(0.0, 0.0, 800.0, 600.0);
+        assert_eq!(Anchor::TopLeft.place((100.0, 50.0), screen), Point2::new(0.0, 0.0));
+        assert_eq!(Anchor::Center.place((100.0, 50.0), screen), Point2::new(350.0, 275.0));
+
+        let rects = Stack::vertical(5.0).layout_in(SIZES, screen, Anchor::Center);
+        assert_eq!(rects[0], Rect::new(350.0, 265.0, 100.0, 20.0));
+    }
+
+    #[test]
+    fn padding_never_shrinks_past_nothing() {
+        let rect = Padding::all(10.0).shrink(Rect::new(0.0, 0.0, 15.0, 100.0));
+        assert_eq!(rect, Rect::new(10.0, 10.0, 0.0, 80.0));
+    }
+}
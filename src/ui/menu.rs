@@ -0,0 +1,377 @@
+//! A vertical list of choices with a focus highlight, shared by
+//! every menu-shaped screen.
+//!
+//! Items can carry callbacks that get the `World` when they're
+//! chosen, which covers most menus (they just queue a scene request).
+//! Screens that need to change their own state instead look at the
+//! `MenuEvent` that `handle_input` returns.
+
+use ggez::graphics::{self, Color, DrawParam, Rect, Text};
+use ggez::{Context, GameResult};
+use log::*;
+
+use crate::input;
+use crate::types::Point2;
+use crate::ui::layout::{self, Align, Stack};
+use crate::ui::theme::ButtonStyle;
+use crate::world::World;
+
+pub type Callback = Box<dyn FnMut(&mut World)>;
+pub type AdjustCallback = Box<dyn FnMut(&mut World, i32)>;
+
+/// Moments a menu might want to make a noise about.  Nothing plays
+/// sounds yet, so they're only logged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuSound {
+    Move,
+    Select,
+    Back,
+    /// Tried to pick a disabled item.
+    Denied,
+}
+
+/// What happened in response to some input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuEvent {
+    None,
+    Moved(usize),
+    Selected(usize),
+    /// Left (-1) or right (1) on an item.
+    Adjusted(usize, i32),
+    Back,
+}
+
+/// How a menu is laid out and how its items look.  Scenes usually
+/// swap in one of the theme's buttons when they draw; see
+/// `Menu::set_button_style`.
+#[derive(Debug, Clone)]
+pub struct MenuStyle {
+    /// Gap between items.
+    pub spacing: f32,
+    pub align: Align,
+    pub button: ButtonStyle,
+}
+
+impl Default for MenuStyle {
+    fn default() -> Self {
+        Self {
+            spacing: 20.0,
+            align: Align::Center,
+            button: ButtonStyle::default(),
+        }
+    }
+}
+
+pub struct MenuItem {
+    pub label: String,
+    pub enabled: bool,
+    on_select: Option<Callback>,
+    on_adjust: Option<AdjustCallback>,
+}
+
+impl MenuItem {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_owned(),
+            enabled: true,
+            on_select: None,
+            on_adjust: None,
+        }
+    }
+
+    pub fn on_select<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut World) + 'static,
+    {
+        self.on_select = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_adjust<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut World, i32) + 'static,
+    {
+        self.on_adjust = Some(Box::new(f));
+        self
+    }
+}
+
+pub struct Menu {
+    items: Vec<MenuItem>,
+    focus: usize,
+    style: MenuStyle,
+    on_back: Option<Callback>,
+}
+
+impl Menu {
+    pub fn new(style: MenuStyle) -> Self {
+        Self {
+            items: Vec::new(),
+            focus: 0,
+            style,
+            on_back: None,
+        }
+    }
+
+    pub fn item(mut self, item: MenuItem) -> Self {
+        self.push(item);
+        self
+    }
+
+    pub fn push(&mut self, item: MenuItem) {
+        let enabled = item.enabled;
+        self.items.push(item);
+        if enabled && !self.items[self.focus].enabled {
+            self.focus = self.items.len() - 1;
+        }
+    }
+
+    /// Called when Back is pressed.
+    pub fn on_back<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&mut World) + 'static,
+    {
+        self.on_back = Some(Box::new(f));
+        self
+    }
+
+    pub fn set_label(&mut self, idx: usize, label: &str) {
+        self.items[idx].label = label.to_owned();
+    }
+
+    /// Greys an item out or brings it back.  Focus moves off an
+    /// item when it's disabled.
+    pub fn set_enabled(&mut self, idx: usize, enabled: bool) {
+        self.items[idx].enabled = enabled;
+        if !self.items[self.focus].enabled {
+            self.move_focus(1);
+        }
+    }
+
+    /// Swaps in a new look for the items.  Scenes call this before
+    /// drawing so a reloaded theme shows up straight away.
+    pub fn set_button_style(&mut self, button: ButtonStyle) {
+        self.style.button = button;
+    }
+
+    fn button(&self) -> &ButtonStyle {
+        &self.style.button
+    }
+
+    /// Moves focus by `step` items, wrapping around and skipping
+    /// disabled items.
+    pub fn move_focus(&mut self, step: i32) -> bool {
+        let len = self.items.len() as i32;
+        if len == 0 {
+            return false;
+        }
+        let mut idx = self.focus as i32;
+        for _ in 0..len {
+            idx = (idx + step).rem_euclid(len);
+            if self.items[idx as usize].enabled {
+                let moved = idx as usize != self.focus;
+                self.focus = idx as usize;
+                return moved;
+            }
+        }
+        false
+    }
+
+    /// Reads the current input state and does whatever it means for
+    /// the menu.  Call this from the owning scene's `input()`.
+    pub fn handle_input(&mut self, world: &mut World) -> MenuEvent {
+        if self.items.is_empty() {
+            return MenuEvent::None;
+        }
+
+        match world.input.get_axis_raw(input::Axis::Vert) {
+            1.0 => return self.moved(-1),
+            -1.0 => return self.moved(1),
+            _ => (),
+        }
+
+        let horz = world.input.get_axis_raw(input::Axis::Horz);
+        if horz == 1.0 || horz == -1.0 {
+            let dir = horz as i32;
+            let focus = self.focus;
+            let item = &mut self.items[focus];
+            if !item.enabled {
+                return MenuEvent::None;
+            }
+            if let Some(ref mut f) = item.on_adjust {
+                f(world, dir);
+            }
+            self.sound(MenuSound::Move);
+            return MenuEvent::Adjusted(focus, dir);
+        }
+
+        if world.input.get_button_pressed(input::Button::Confirm)
+            || world.input.get_button_pressed(input::Button::Select)
+        {
+            let focus = self.focus;
+            if !self.items[focus].enabled {
+                self.sound(MenuSound::Denied);
+                return MenuEvent::None;
+            }
+            self.sound(MenuSound::Select);
+            if let Some(ref mut f) = self.items[focus].on_select {
+                f(world);
+            }
+            return MenuEvent::Selected(focus);
+        }
+
+        if world.input.get_button_pressed(input::Button::Back) {
+            self.sound(MenuSound::Back);
+            if let Some(ref mut f) = self.on_back {
+                f(world);
+            }
+            return MenuEvent::Back;
+        }
+
+        MenuEvent::None
+    }
+
+    fn moved(&mut self, step: i32) -> MenuEvent {
+        if self.move_focus(step) {
+            self.sound(MenuSound::Move);
+            MenuEvent::Moved(self.focus)
+        } else {
+            MenuEvent::None
+        }
+    }
+
+    fn sound(&mut self, sound: MenuSound) {
+        trace!("Menu sound: {:?}", sound);
+    }
+
+    /// The color an item is drawn in right now, for screens that
+    /// draw extra things next to items and want them to match.
+    pub fn item_color(&self, idx: usize) -> Color {
+        let button = self.button();
+        if !self.items[idx].enabled {
+            button.disabled_color
+        } else if idx == self.focus {
+            button.focused_color
+        } else {
+            button.unfocused_color
+        }
+    }
+
+    fn texts(&self) -> Vec<Text> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let button = self.button();
+                button.font.colored(&item.label, button.scale, self.item_color(i))
+            })
+            .collect()
+    }
+
+    fn stack(&self) -> Stack {
+        Stack::vertical(self.style.spacing).align(self.style.align)
+    }
+
+    /// Item sizes, with room for their panels.
+    fn item_sizes(&self, ctx: &mut Context, texts: &[Text]) -> Vec<(f32, f32)> {
+        let pad = 2.0 * self.button().padding;
+        texts
+            .iter()
+            .map(|t| {
+                let (w, h) = layout::measure_text(ctx, t);
+                (w + pad, h + pad)
+            })
+            .collect()
+    }
+
+    /// Size of the whole menu, for fitting it into a bigger layout.
+    pub fn size(&self, ctx: &mut Context) -> (f32, f32) {
+        let sizes = self.item_sizes(ctx, &self.texts());
+        self.stack().size(&sizes)
+    }
+
+    /// Draws the menu with its top-left corner at `origin`.  Returns
+    /// where each item went, for screens that draw extra things
+    /// next to them.
+    pub fn draw(&self, ctx: &mut Context, origin: Point2<f32>) -> GameResult<Vec<Rect>> {
+        let texts = self.texts();
+        let sizes = self.item_sizes(ctx, &texts);
+        let rects = self.stack().layout(&sizes, origin);
+        let button = self.button();
+        for (i, (text, rect)) in texts.iter().zip(rects.iter()).enumerate() {
+            let focused = i == self.focus && self.items[i].enabled;
+            let panel = if focused {
+                button.focused_panel.as_ref().or(button.panel.as_ref())
+            } else {
+                button.panel.as_ref()
+            };
+            if let Some(panel) = panel {
+                panel.draw(ctx, *rect)?;
+            }
+            let dest = Point2::new(rect.x + button.padding, rect.y + button.padding);
+            graphics::draw(ctx, text, DrawParam::default().dest(dest))?;
+        }
+        Ok(rects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A menu with an item per label, where labels starting with `-`
+    /// are disabled.
+    fn menu_of(labels: &[&str]) -> Menu {
+        let mut menu = Menu::new(MenuStyle::default());
+        for label in labels {
+            menu.push(MenuItem::new(label));
+        }
+        for (i, label) in labels.iter().enumerate() {
+            menu.set_enabled(i, !label.starts_with('-'));
+        }
+        menu
+    }
+
+    #[test]
+    fn focus_wraps_around_both_ends() {
+        let mut menu = menu_of(&["a", "b", "c"]);
+        assert!(menu.move_focus(-1));
+        assert_eq!(menu.focus, 2);
+        assert!(menu.move_focus(1));
+        assert_eq!(menu.focus, 0);
+    }
+
+    #[test]
+    fn focus_skips_disabled_items() {
+        let mut menu = menu_of(&["-a", "b", "-c", "d"]);
+        assert_eq!(menu.focus, 1);
+        assert!(menu.move_focus(1));
+        assert_eq!(menu.focus, 3);
+        assert!(menu.move_focus(1));
+        assert_eq!(menu.focus, 1);
+        assert!(menu.move_focus(-1));
+        assert_eq!(menu.focus, 3);
+    }
+
+    #[test]
+    fn focus_stays_put_with_nowhere_to_go() {
+        let mut menu = menu_of(&["-a", "b", "-c"]);
+        assert!(!menu.move_focus(1));
+        assert_eq!(menu.focus, 1);
+
+        let mut all_disabled = menu_of(&["-a", "-b"]);
+        assert!(!all_disabled.move_focus(1));
+        assert!(!Menu::new(MenuStyle::default()).move_focus(1));
+    }
+
+    #[test]
+    fn disabling_the_focused_item_moves_focus() {
+        let mut menu = menu_of(&["a", "b", "c"]);
+        menu.set_enabled(0, false);
+        assert_eq!(menu.focus, 1);
+        menu.set_enabled(2, false);
+        assert_eq!(menu.focus, 1);
+        menu.set_enabled(0, true);
+        assert_eq!(menu.focus, 1);
+    }
+}
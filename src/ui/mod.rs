@@ -0,0 +1,11 @@
+//! Reusable UI widgets that scenes build their screens out of.
+
+pub mod font;
+pub mod layout;
+pub mod menu;
+pub mod text_field;
+pub mod theme;
+
+pub use self::menu::{Menu, MenuEvent, MenuItem, MenuStyle};
+pub use self::text_field::{TextField, TextFieldStyle};
+pub use self::theme::Theme;
@@ -0,0 +1,546 @@
+//! A single-line editable text box.
+//!
+//! Editing works on grapheme clusters rather than chars, so accented
+//! letters, flags and other multi-codepoint things move and delete as
+//! one unit.  The field doesn't listen to events itself; the owning
+//! scene feeds it typed characters and raw key presses.
+
+use std::time::Duration;
+
+use clipboard::{ClipboardContext, ClipboardProvider};
+use ggez::event::{KeyCode, KeyMods};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect};
+use ggez::{timer, Context, GameResult};
+use log::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::input::KeyPress;
+use crate::types::Point2;
+use crate::ui::font::FontStack;
+use crate::util;
+
+/// Seconds the caret stays on, and then off, while blinking.
+const BLINK_HALF_PERIOD: f64 = 0.5;
+
+/// Width of the caret bar, in pixels.
+const CARET_WIDTH: f32 = 2.0;
+
+/// How a text field looks.
+#[derive(Debug, Clone)]
+pub struct TextFieldStyle {
+    pub font: FontStack,
+    pub scale: f32,
+    pub text_color: Color,
+    pub caret_color: Color,
+    pub selection_color: Color,
+    /// Space between the box edge and the text.
+    pub padding: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextField {
+    text: String,
+    /// Byte offset of the caret.  Always on a grapheme boundary.
+    cursor: usize,
+    /// The other end of the selection, if one is being made.
+    anchor: Option<usize>,
+    /// Byte offset of the first grapheme drawn, for when the text
+    /// is wider than the box.
+    scroll: usize,
+    /// Widest the text can get, in display columns (see
+    /// `util::display_width`).
+    max_len: usize,
+    /// Set on every edit so the caret restarts its blink solid.
+    caret_moved: bool,
+    blink_epoch: Duration,
+}
+
+impl TextField {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            text: String::new(),
+            cursor: 0,
+            anchor: None,
+            scroll: 0,
+            max_len,
+            caret_moved: true,
+            blink_epoch: Duration::from_secs(0),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Width in display columns, which is what `max_len` counts.
+    pub fn width(&self) -> usize {
+        util::display_width(&self.text)
+    }
+
+    /// Replaces the whole contents and puts the caret at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.clear();
+        self.insert_str(text);
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+        self.anchor = None;
+        self.scroll = 0;
+        self.caret_moved = true;
+    }
+
+    /// The selected byte range, if anything is selected.
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        match self.anchor {
+            Some(anchor) if anchor != self.cursor => {
+                Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|(start, end)| &self.text[start..end])
+    }
+
+    /// Types a character at the caret, replacing any selection.
+    /// Control characters are ignored, since ggez also sends those
+    /// through for things like backspace and return.
+    pub fn insert_char(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+        let mut buf = [0; 4];
+        self.insert_str(c.encode_utf8(&mut buf));
+    }
+
+    /// Inserts a string at the caret, replacing any selection and
+    /// cutting off whatever doesn't fit under `max_len`.
+    pub fn insert_str(&mut self, s: &str) {
+        self.delete_selection();
+        let cleaned: String = s.chars().filter(|c| !c.is_control()).collect();
+        let mut room = self.max_len.saturating_sub(self.width());
+        let mut end = 0;
+        for grapheme in cleaned.graphemes(true) {
+            let width = util::grapheme_width(grapheme);
+            if width > room {
+                break;
+            }
+            room -= width;
+            end += grapheme.len();
+        }
+        self.text.insert_str(self.cursor, &cleaned[..end]);
+        self.cursor += end;
+        self.caret_moved = true;
+    }
+
+    /// Deletes the selection, or the grapheme before the caret.
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            let prev = self.prev_boundary(self.cursor);
+            self.text.replace_range(prev..self.cursor, "");
+            self.cursor = prev;
+        }
+        self.caret_moved = true;
+    }
+
+    /// Deletes the selection, or the grapheme after the caret.
+    pub fn delete_forward(&mut self) {
+        if !self.delete_selection() && self.cursor < self.text.len() {
+            let next = self.next_boundary(self.cursor);
+            self.text.replace_range(self.cursor..next, "");
+        }
+        self.caret_moved = true;
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        match self.selection() {
+            Some((start, _)) if !select => self.set_cursor(start, false),
+            _ => {
+                let prev = self.prev_boundary(self.cursor);
+                self.set_cursor(prev, select);
+            }
+        }
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        match self.selection() {
+            Some((_, end)) if !select => self.set_cursor(end, false),
+            _ => {
+                let next = self.next_boundary(self.cursor);
+                self.set_cursor(next, select);
+            }
+        }
+    }
+
+    pub fn home(&mut self, select: bool) {
+        self.set_cursor(0, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        let len = self.text.len();
+        self.set_cursor(len, select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+        self.caret_moved = true;
+    }
+
+    pub fn copy(&self) {
+        if let (Some(selected), Some(mut clipboard)) = (self.selected_text(), open_clipboard()) {
+            if let Err(e) = clipboard.set_contents(selected.to_owned()) {
+                warn!("Could not copy to clipboard: {}", e);
+            }
+        }
+    }
+
+    pub fn cut(&mut self) {
+        self.copy();
+        self.delete_selection();
+        self.caret_moved = true;
+    }
+
+    pub fn paste(&mut self) {
+        if let Some(mut clipboard) = open_clipboard() {
+            match clipboard.get_contents() {
+                Ok(contents) => self.insert_str(&contents),
+                Err(e) => warn!("Could not paste from clipboard: {}", e),
+            }
+        }
+    }
+
+    /// Handles an editing key.  Returns whether the key meant
+    /// anything to the field.
+    pub fn handle_key(&mut self, key: KeyPress) -> bool {
+        let shift = key.mods.contains(KeyMods::SHIFT);
+        let ctrl = key.mods.contains(KeyMods::CTRL) || key.mods.contains(KeyMods::LOGO);
+        match key.keycode {
+            KeyCode::Left => self.move_left(shift),
+            KeyCode::Right => self.move_right(shift),
+            KeyCode::Home => self.home(shift),
+            KeyCode::End => self.end(shift),
+            KeyCode::Back => self.backspace(),
+            KeyCode::Delete => self.delete_forward(),
+            KeyCode::A if ctrl => self.select_all(),
+            KeyCode::C if ctrl => self.copy(),
+            KeyCode::X if ctrl => self.cut(),
+            KeyCode::V if ctrl => self.paste(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the text, selection and caret inside `rect`.  The box
+    /// itself is up to the caller.
+    pub fn draw(&mut self, ctx: &mut Context, rect: Rect, style: &TextFieldStyle) -> GameResult<()> {
+        let now = timer::time_since_start(ctx);
+        if self.caret_moved {
+            self.blink_epoch = now;
+            self.caret_moved = false;
+        }
+
+        let inner_width = rect.w - 2.0 * style.padding;
+        let visible_end = {
+            let mut measure = |s: &str| measure(ctx, style, s);
+            self.scroll_to_caret(inner_width, &mut measure);
+            self.visible_end(inner_width, &mut measure)
+        };
+        let origin = Point2::new(
+            rect.x + style.padding,
+            rect.y + (rect.h - style.scale) / 2.0,
+        );
+
+        if let Some((start, end)) = self.selection() {
+            let start = start.max(self.scroll).min(visible_end);
+            let end = end.max(self.scroll).min(visible_end);
+            if end > start {
+                let x0 = measure(ctx, style, &self.text[self.scroll..start]);
+                let x1 = measure(ctx, style, &self.text[self.scroll..end]);
+                let highlight = Mesh::new_rectangle(
+                    ctx,
+                    DrawMode::fill(),
+                    Rect::new(origin.x + x0, origin.y, x1 - x0, style.scale),
+                    style.selection_color,
+                )?;
+                graphics::draw(ctx, &highlight, DrawParam::default())?;
+            }
+        }
+
+        let text = style
+            .font
+            .colored(&self.text[self.scroll..visible_end], style.scale, style.text_color);
+        graphics::draw(ctx, &text, DrawParam::default().dest(origin))?;
+
+        let blink_time = timer::duration_to_f64(now - self.blink_epoch);
+        if (blink_time / BLINK_HALF_PERIOD) as u64 % 2 == 0 {
+            let x = measure(ctx, style, &self.text[self.scroll..self.cursor]);
+            let caret = Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                Rect::new(origin.x + x, origin.y, CARET_WIDTH, style.scale),
+                style.caret_color,
+            )?;
+            graphics::draw(ctx, &caret, DrawParam::default())?;
+        }
+        Ok(())
+    }
+
+    fn set_cursor(&mut self, pos: usize, select: bool) {
+        if select {
+            if self.anchor.is_none() {
+                self.anchor = Some(self.cursor);
+            }
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = pos;
+        self.caret_moved = true;
+    }
+
+    /// Removes the selected text, if any.  Returns whether there was
+    /// anything to remove.
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+        match selection {
+            Some((start, end)) => {
+                self.text.replace_range(start..end, "");
+                self.cursor = start;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn prev_boundary(&self, pos: usize) -> usize {
+        self.text[..pos]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .graphemes(true)
+            .next()
+            .map(|g| pos + g.len())
+            .unwrap_or(pos)
+    }
+
+    /// Moves `scroll` so the caret is inside the box, and so we don't
+    /// leave empty space on the right when there's hidden text on
+    /// the left.
+    fn scroll_to_caret<F>(&mut self, width: f32, measure: &mut F)
+    where
+        F: FnMut(&str) -> f32,
+    {
+        while self.scroll > 0 {
+            let prev = self.prev_boundary(self.scroll);
+            if measure(&self.text[prev..]) > width {
+                break;
+            }
+            self.scroll = prev;
+        }
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        }
+        while self.scroll < self.cursor
+            && measure(&self.text[self.scroll..self.cursor]) > width
+        {
+            self.scroll = self.next_boundary(self.scroll);
+        }
+    }
+
+    /// Byte offset just past the last grapheme that fits in the box.
+    fn visible_end<F>(&self, width: f32, measure: &mut F) -> usize
+    where
+        F: FnMut(&str) -> f32,
+    {
+        let mut end = self.scroll;
+        for (i, grapheme) in self.text[self.scroll..].grapheme_indices(true) {
+            let next = self.scroll + i + grapheme.len();
+            if measure(&self.text[self.scroll..next]) > width {
+                break;
+            }
+            end = next;
+        }
+        end.max(self.cursor)
+    }
+}
+
+/// Width of `s` as the field would draw it.  The glyph bounds ggez
+/// measures don't include trailing spaces, so we measure with a
+/// marker on the end and take its width back off.
+fn measure(ctx: &mut Context, style: &TextFieldStyle, s: &str) -> f32 {
+    if s.is_empty() {
+        return 0.0;
+    }
+    let mut width = |s: &str| style.font.text(s, style.scale).width(ctx) as f32;
+    width(&format!("{}|", s)) - width("|")
+}
+
+fn open_clipboard() -> Option<ClipboardContext> {
+    match ClipboardProvider::new() {
+        Ok(clipboard) => Some(clipboard),
+        Err(e) => {
+            warn!("Could not open clipboard: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten pixels a display column, so widths are easy to work out.
+    fn measure(s: &str) -> f32 {
+        util::display_width(s) as f32 * 10.0
+    }
+
+    fn field(text: &str) -> TextField {
+        let mut field = TextField::new(20);
+        field.set_text(text);
+        field
+    }
+
+    fn press(field: &mut TextField, keycode: KeyCode, mods: KeyMods) -> bool {
+        field.handle_key(KeyPress {
+            keycode,
+            mods,
+            repeat: false,
+        })
+    }
+
+    #[test]
+    fn editing_moves_over_whole_graphemes() {
+        // An e with a combining accent, and a flag made of two
+        // regional indicators.
+        let mut field = field("ae\u{301}\u{1F1F3}\u{1F1FF}");
+        field.backspace();
+        assert_eq!(field.text(), "ae\u{301}");
+        field.move_left(false);
+        assert_eq!(field.cursor, 1);
+        field.delete_forward();
+        assert_eq!(field.text(), "a");
+        field.delete_forward();
+        assert_eq!(field.text(), "a");
+        field.home(false);
+        field.backspace();
+        assert_eq!(field.text(), "a");
+    }
+
+    #[test]
+    fn control_characters_are_not_typed() {
+        let mut field = field("");
+        field.insert_char('\u{8}');
+        field.insert_char('\r');
+        field.insert_char('a');
+        field.insert_str("b\nc\td");
+        assert_eq!(field.text(), "abcd");
+    }
+
+    #[test]
+    fn text_is_cut_off_at_max_len() {
+        let mut field = TextField::new(5);
+        field.insert_str("abcdefg");
+        assert_eq!(field.text(), "abcde");
+        field.insert_char('f');
+        assert_eq!(field.text(), "abcde");
+
+        // Wide characters take two columns each.
+        let mut field = TextField::new(5);
+        field.insert_str("爷爷爷");
+        assert_eq!(field.text(), "爷爷");
+        assert_eq!(field.width(), 4);
+        field.insert_char('a');
+        assert_eq!(field.text(), "爷爷a");
+    }
+
+    #[test]
+    fn typing_replaces_the_selection() {
+        let mut field = field("hello");
+        field.home(false);
+        field.move_right(true);
+        field.move_right(true);
+        assert_eq!(field.selected_text(), Some("he"));
+        field.insert_char('J');
+        assert_eq!(field.text(), "Jllo");
+        assert_eq!(field.selection(), None);
+
+        field.select_all();
+        assert_eq!(field.selected_text(), Some("Jllo"));
+        field.backspace();
+        assert_eq!(field.text(), "");
+    }
+
+    #[test]
+    fn moving_without_shift_drops_the_selection() {
+        let mut field = field("hello");
+        field.move_left(true);
+        field.move_left(true);
+        field.move_left(false);
+        assert_eq!((field.cursor, field.selection()), (3, None));
+
+        field.end(false);
+        field.home(true);
+        field.move_right(false);
+        assert_eq!((field.cursor, field.selection()), (5, None));
+    }
+
+    #[test]
+    fn keys_edit_and_select() {
+        let mut field = field("hello world");
+        assert!(press(&mut field, KeyCode::Left, KeyMods::SHIFT));
+        assert!(press(&mut field, KeyCode::Left, KeyMods::SHIFT));
+        assert_eq!(field.selected_text(), Some("ld"));
+        assert!(press(&mut field, KeyCode::Back, KeyMods::empty()));
+        assert_eq!(field.text(), "hello wor");
+        assert!(press(&mut field, KeyCode::A, KeyMods::CTRL));
+        assert_eq!(field.selected_text(), Some("hello wor"));
+        assert!(!press(&mut field, KeyCode::A, KeyMods::empty()));
+        assert!(!press(&mut field, KeyCode::Return, KeyMods::empty()));
+    }
+
+    #[test]
+    fn pasted_text_replaces_the_selection_and_fits_in() {
+        // What `paste` does with the clipboard's contents.
+        let mut field = TextField::new(12);
+        field.set_text("hello world");
+        for _ in 0..5 {
+            field.move_left(true);
+        }
+        field.insert_str("there\nand everywhere");
+        assert_eq!(field.text(), "hello therea");
+        assert_eq!(field.cursor, field.text().len());
+    }
+
+    #[test]
+    fn scrolling_keeps_the_caret_in_view() {
+        let mut field = field("abcdefghij");
+        field.scroll_to_caret(45.0, &mut measure);
+        assert_eq!(field.scroll, 6);
+        assert_eq!(field.visible_end(45.0, &mut measure), 10);
+
+        field.home(false);
+        field.scroll_to_caret(45.0, &mut measure);
+        assert_eq!(field.scroll, 0);
+        assert_eq!(field.visible_end(45.0, &mut measure), 4);
+
+        // Deleting from the end scrolls back rather than leaving a
+        // gap on the right.
+        field.end(false);
+        field.scroll_to_caret(45.0, &mut measure);
+        for _ in 0..3 {
+            field.backspace();
+        }
+        field.scroll_to_caret(45.0, &mut measure);
+        assert_eq!(field.scroll, 3);
+        assert_eq!(&field.text()[field.scroll..], "defg");
+    }
+}
@@ -0,0 +1,426 @@
+//! The look of the UI: colors, fonts, text sizes, panel images and
+//! button styles, all read from `/theme.ron` so they can be changed
+//! without recompiling.
+//!
+//! The theme is loaded through the resource store, so editing the
+//! file while the game runs swaps the new look in on the next sync.
+//! Scenes should read it from `World::theme` every time they draw
+//! rather than holding on to colors or fonts.  Only the `.ron` file
+//! itself is watched; touch it after changing a font or image.
+
+use std::cell::Ref;
+use std::collections::BTreeMap;
+
+use ggez::graphics::{self, Color, DrawParam, Rect};
+use ggez::nalgebra::Vector2;
+use ggez::{Context, GameResult};
+use log::*;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use warmy;
+
+use crate::resources::{self, Key, Loaded, Storage};
+use crate::types::{Error, Point2};
+use crate::ui::font::FontStack;
+
+pub const THEME_PATH: &str = "/theme.ron";
+
+/// Every font, panel and button map in the theme has to have one of
+/// these, and lookups of names that aren't there fall back to it.
+pub const DEFAULT: &str = "default";
+
+/// Named colors.  Written as `"#rrggbb"` or `"#rrggbbaa"`.
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct Palette {
+    /// Behind every scene that doesn't clear the screen itself.
+    #[serde(deserialize_with = "hex_color")]
+    pub clear: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub input_box: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub selection: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub error: Color,
+    /// Laid over whatever is under a menu that pops up on top.
+    #[serde(deserialize_with = "hex_color")]
+    pub dim: Color,
+    /// Around the edges of the level, where there's no map.
+    #[serde(deserialize_with = "hex_color")]
+    pub level: Color,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            clear: Color::from_rgba(0x00, 0x00, 0x66, 0x00),
+            background: Color::from_rgb(0x29, 0x1f, 0x1e),
+            input_box: Color::from_rgb(0x32, 0x32, 0x32),
+            text: graphics::WHITE,
+            selection: Color::from_rgb(0x47, 0x79, 0x98),
+            error: Color::from_rgb(0xe6, 0x59, 0x4d),
+            dim: Color::from_rgba(0x00, 0x00, 0x00, 0x99),
+            level: graphics::BLACK,
+        }
+    }
+}
+
+/// Text sizes, in pixels.
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct Sizes {
+    pub display: f32,
+    pub title: f32,
+    pub heading: f32,
+    pub body: f32,
+    pub small: f32,
+}
+
+impl Default for Sizes {
+    fn default() -> Self {
+        Self {
+            display: 48.0,
+            title: 36.0,
+            heading: 28.0,
+            body: 20.0,
+            small: 16.0,
+        }
+    }
+}
+
+/// An image drawn stretched to any size without stretching its
+/// corners.  The edges stretch along their length and the middle
+/// fills the rest.
+#[derive(Debug, Clone)]
+pub struct NineSlice {
+    pub image: graphics::Image,
+    /// Width of the corners and edges, in image pixels.
+    pub border: f32,
+    /// Tint.
+    pub color: Color,
+}
+
+impl NineSlice {
+    pub fn draw(&self, ctx: &mut Context, rect: Rect) -> GameResult<()> {
+        let (iw, ih) = (self.image.width() as f32, self.image.height() as f32);
+        let b = self.border.min(iw / 2.0).min(ih / 2.0);
+        let src_x = [0.0, b, iw - b, iw];
+        let src_y = [0.0, b, ih - b, ih];
+        let dst_x = [rect.x, rect.x + b, rect.x + rect.w - b, rect.x + rect.w];
+        let dst_y = [rect.y, rect.y + b, rect.y + rect.h - b, rect.y + rect.h];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let (sw, sh) = (src_x[col + 1] - src_x[col], src_y[row + 1] - src_y[row]);
+                let (dw, dh) = (dst_x[col + 1] - dst_x[col], dst_y[row + 1] - dst_y[row]);
+                if sw <= 0.0 || sh <= 0.0 || dw <= 0.0 || dh <= 0.0 {
+                    continue;
+                }
+                let param = DrawParam::default()
+                    .src(Rect::new(src_x[col] / iw, src_y[row] / ih, sw / iw, sh / ih))
+                    .dest(Point2::new(dst_x[col], dst_y[row]))
+                    .scale(Vector2::new(dw / sw, dh / sh))
+                    .color(self.color);
+                graphics::draw(ctx, &self.image, param)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How the items of a menu look.
+#[derive(Debug, Clone)]
+pub struct ButtonStyle {
+    pub font: FontStack,
+    pub scale: f32,
+    pub focused_color: Color,
+    pub unfocused_color: Color,
+    pub disabled_color: Color,
+    /// Drawn behind every item.
+    pub panel: Option<NineSlice>,
+    /// Drawn behind the focused item instead of `panel`.
+    pub focused_panel: Option<NineSlice>,
+    /// Space between the panel edge and the label.
+    pub padding: f32,
+}
+
+impl Default for ButtonStyle {
+    /// Plain text in ggez's built-in font, for menus drawn before
+    /// anything's picked a theme button for them.
+    fn default() -> Self {
+        Self {
+            font: FontStack::default(),
+            scale: 20.0,
+            focused_color: graphics::WHITE,
+            unfocused_color: Color::from_rgb(0xbc, 0xbc, 0xbc),
+            disabled_color: Color::from_rgb(0x64, 0x64, 0x64),
+            panel: None,
+            focused_panel: None,
+            padding: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub palette: Palette,
+    pub sizes: Sizes,
+    fonts: BTreeMap<String, FontStack>,
+    panels: BTreeMap<String, NineSlice>,
+    buttons: BTreeMap<String, ButtonStyle>,
+}
+
+impl Theme {
+    pub fn font(&self, name: &str) -> &FontStack {
+        lookup(&self.fonts, name)
+    }
+
+    /// `None` only in the default theme, which has no images.
+    pub fn panel(&self, name: &str) -> Option<&NineSlice> {
+        self.panels.get(name).or_else(|| self.panels.get(DEFAULT))
+    }
+
+    pub fn button(&self, name: &str) -> ButtonStyle {
+        lookup(&self.buttons, name).clone()
+    }
+
+    /// Turns the file's names into loaded fonts and images.
+    fn resolve(ctx: &mut Context, file: ThemeFile) -> Result<Self, Error> {
+        let mut fonts = BTreeMap::new();
+        for (name, paths) in file.fonts {
+            let font = FontStack::load(ctx, &paths)?;
+            fonts.insert(name, font);
+        }
+
+        let mut panels = BTreeMap::new();
+        for (name, panel) in file.panels {
+            let image = graphics::Image::new(ctx, &panel.image).map_err(Error::Ggez)?;
+            panels.insert(
+                name,
+                NineSlice {
+                    image,
+                    border: panel.border,
+                    color: panel.color,
+                },
+            );
+        }
+
+        let find_panel = |name: &Option<String>| -> Result<Option<NineSlice>, Error> {
+            match name {
+                Some(name) => panels
+                    .get(name)
+                    .cloned()
+                    .map(Some)
+                    .ok_or_else(|| Error::Theme(format!("no panel named {:?}", name))),
+                None => Ok(None),
+            }
+        };
+        let mut buttons = BTreeMap::new();
+        for (name, button) in file.buttons {
+            let font = fonts
+                .get(&button.font)
+                .cloned()
+                .ok_or_else(|| Error::Theme(format!("no font named {:?}", button.font)))?;
+            let style = ButtonStyle {
+                font,
+                scale: button.size,
+                focused_color: button.focused,
+                unfocused_color: button.unfocused,
+                disabled_color: button.disabled,
+                panel: find_panel(&button.panel)?,
+                focused_panel: find_panel(&button.focused_panel)?,
+                padding: button.padding,
+            };
+            buttons.insert(name, style);
+        }
+
+        for (kind, has_default) in &[
+            ("font", fonts.contains_key(DEFAULT)),
+            ("panel", panels.contains_key(DEFAULT)),
+            ("button", buttons.contains_key(DEFAULT)),
+        ] {
+            if !has_default {
+                return Err(Error::Theme(format!("no {} named {:?}", kind, DEFAULT)));
+            }
+        }
+
+        Ok(Theme {
+            palette: file.palette,
+            sizes: file.sizes,
+            fonts,
+            panels,
+            buttons,
+        })
+    }
+}
+
+impl Default for Theme {
+    /// The look with no theme file: ggez's built-in font, the stock
+    /// colors and no panels.
+    fn default() -> Self {
+        let mut fonts = BTreeMap::new();
+        fonts.insert(DEFAULT.to_owned(), FontStack::default());
+        let mut buttons = BTreeMap::new();
+        buttons.insert(DEFAULT.to_owned(), ButtonStyle::default());
+        Theme {
+            palette: Palette::default(),
+            sizes: Sizes::default(),
+            fonts,
+            panels: BTreeMap::new(),
+            buttons,
+        }
+    }
+}
+
+/// Looks a name up, falling back to `DEFAULT`, which `resolve` and
+/// `Theme::default` make sure is there.
+fn lookup<'a, T>(map: &'a BTreeMap<String, T>, name: &str) -> &'a T {
+    map.get(name).unwrap_or_else(|| &map[DEFAULT])
+}
+
+impl warmy::Load<Context, Key> for Theme {
+    type Error = Error;
+    fn load(key: Key, _storage: &mut Storage, ctx: &mut Context) -> Result<Loaded<Self>, Self::Error> {
+        debug!("Loading theme {:?}", key);
+
+        match key {
+            Key::Path(path) => {
+                let theme_file: ThemeFile = resources::load_ron(ctx, &path)?;
+                Theme::resolve(ctx, theme_file).map(warmy::Loaded::from)
+            }
+        }
+    }
+}
+
+/// How `World` holds the theme.  Running headless there's no
+/// theme file, so it's the default theme.
+pub struct ThemeHandle(warmy::Res<Theme>);
+
+impl ThemeHandle {
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        ThemeHandle(warmy::Res::new(Theme::default()))
+    }
+
+    /// The theme as it is right now.
+    pub fn borrow(&self) -> Ref<'_, Theme> {
+        self.0.borrow()
+    }
+}
+
+/// Loads the theme into the store.  Later changes to the file show
+/// up in the returned handle.
+pub fn load(ctx: &mut Context, store: &mut resources::Store) -> ThemeHandle {
+    let theme = store
+        .get::<Theme>(&Key::from_path(THEME_PATH), ctx)
+        .expect("Could not load the UI theme");
+    ThemeHandle(theme)
+}
+
+/// The theme as it's written in the file, with fonts, panels and
+/// buttons still referred to by name.
+#[derive(Debug, Clone, Deserialize)]
+struct ThemeFile {
+    palette: Palette,
+    /// Name to font paths, in fallback order.
+    fonts: BTreeMap<String, Vec<String>>,
+    sizes: Sizes,
+    panels: BTreeMap<String, PanelFile>,
+    buttons: BTreeMap<String, ButtonFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PanelFile {
+    image: String,
+    border: f32,
+    #[serde(default = "white", deserialize_with = "hex_color")]
+    color: Color,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ButtonFile {
+    font: String,
+    size: f32,
+    #[serde(deserialize_with = "hex_color")]
+    focused: Color,
+    #[serde(deserialize_with = "hex_color")]
+    unfocused: Color,
+    #[serde(deserialize_with = "hex_color")]
+    disabled: Color,
+    #[serde(default)]
+    panel: Option<String>,
+    #[serde(default)]
+    focused_panel: Option<String>,
+    #[serde(default)]
+    padding: f32,
+}
+
+fn white() -> Color {
+    graphics::WHITE
+}
+
+fn hex_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_hex(&s).ok_or_else(|| {
+        de::Error::custom(format!(
+            "bad color {:?}, expected \"#rrggbb\" or \"#rrggbbaa\"",
+            s
+        ))
+    })
+}
+
+fn parse_hex(s: &str) -> Option<Color> {
+    let digits = s.strip_prefix('#')?;
+    let byte = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+    match digits.len() {
+        6 => Some(Color::from_rgb(byte(0)?, byte(2)?, byte(4)?)),
+        8 => Some(Color::from_rgba(byte(0)?, byte(2)?, byte(4)?, byte(6)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::ui::font;
+
+    #[test]
+    fn the_headless_theme_has_everything_but_panels() {
+        let handle = ThemeHandle::headless();
+        let theme = handle.borrow();
+        assert_eq!(theme.font("mono").primary(), graphics::Font::default());
+        assert_eq!(theme.button("pause").scale, 20.0);
+        assert!(theme.panel("window").is_none());
+    }
+
+    #[test]
+    fn default_fonts_cover_cjk_and_emoji() {
+        let resource_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let file: ThemeFile = resources::read_ron(&resource_dir, THEME_PATH).unwrap();
+        let paths = &file.fonts[DEFAULT];
+        let fonts: Vec<rusttype::Font> = paths
+            .iter()
+            .map(|path| {
+                let bytes = std::fs::read(resource_dir.join(&path[1..]))
+                    .unwrap_or_else(|e| panic!("could not read {}: {}", path, e));
+                rusttype::Font::from_bytes(bytes).unwrap_or_else(|e| panic!("could not parse {}: {}", path, e))
+            })
+            .collect();
+        let font_for = |text: &str| font::pick(fonts.iter(), text).map(|i| paths[i].as_str());
+
+        assert_eq!(font_for("G"), Some("/fonts/DejaVuSerif.ttf"));
+        assert_eq!(font_for("グ"), Some("/fonts/UnifontCJK.ttf"));
+        assert_eq!(font_for("爷"), Some("/fonts/UnifontCJK.ttf"));
+        assert_eq!(font_for("한"), Some("/fonts/UnifontCJK.ttf"));
+        // DejaVu Sans has the older emoji itself.
+        assert_eq!(font_for("😀"), Some("/fonts/DejaVuSans.ttf"));
+        assert_eq!(font_for("🌳"), Some("/fonts/NotoEmoji-Regular.ttf"));
+    }
+}
@@ -1,36 +1,43 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// How many times a second we run game logic, unless the settings
+/// say otherwise.
+pub const DESIRED_FPS: u32 = 60;
+
 /// A couple handy re-exports from Euclid
 pub use euclid::point2;
 pub use euclid::vec2;
 
-/// Basic logging setup to log to the console with `fern`.
-pub fn setup_logging() {
-    use fern::colors::{Color, ColoredLevelConfig};
-    let colors = ColoredLevelConfig::default()
-        .info(Color::Green)
-        .debug(Color::BrightMagenta)
-        .trace(Color::BrightBlue);
-    // This sets up a `fern` logger and initializes `log`.
-    fern::Dispatch::new()
-        // Formats logs
-        .format(move |out, message, record| {
-            out.finish(format_args!(
-                "[{}][{:<5}][{}] {}",
-                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
-                colors.color(record.level()),
-                record.target(),
-                message
-            ))
-        })
-        .level(log::LevelFilter::Warn)
-        // Filter out unnecessary stuff
-        .level_for("gfx", log::LevelFilter::Off)
-        // .level_for("walk", log::LevelFilter::Warn)
-        // Set levels for stuff we care about
-        .level_for("threething", log::LevelFilter::Trace)
-        // Hooks up console output.
-        // env var for outputting to a file?
-        // Haven't needed it yet!
-        .chain(std::io::stdout())
-        .apply()
-        .expect("Could not init logging!");
+/// Where we keep files that belong to the player rather than the
+/// game: saves, settings, logs.  Falls back to the working
+/// directory if the OS won't tell us.
+pub fn user_data_dir() -> std::path::PathBuf {
+    match directories::ProjectDirs::from("", "tlboright", "grandpas-big-adventure") {
+        Some(dirs) => dirs.data_dir().to_owned(),
+        None => std::path::PathBuf::from("."),
+    }
+}
+
+/// How many columns a grapheme takes up: one for most letters, two
+/// for wide ones like CJK ideographs and emoji, zero for things that
+/// don't show up at all.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    // Emoji presentation selector; makes things like U+2764 draw as
+    // a wide emoji rather than a narrow symbol.
+    if grapheme.contains('\u{FE0F}') {
+        return 2;
+    }
+    // Combining marks and joined emoji sequences take the width of
+    // the thing they're attached to.
+    grapheme
+        .chars()
+        .next()
+        .and_then(UnicodeWidthChar::width)
+        .unwrap_or(0)
+}
+
+/// How many columns a string takes up; see `grapheme_width`.
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
 }
@@ -1,38 +1,136 @@
-use crate::{input, resources, util};
+use crate::{animation, components, game_state, input, locale, prefab, resources, scenes, settings, timestep, ui, util};
 
 use log::*;
-use specs::{self, world::Builder};
+use rand::{rngs::StdRng, SeedableRng};
+use specs::{self, Builder, Join};
 use warmy;
 
 use std::path;
 
 pub struct World {
     pub resources: resources::Store,
+    /// Reloads itself when the theme file changes, so borrow it
+    /// fresh each draw.
+    pub theme: ui::theme::ThemeHandle,
+    /// All the text the player sees, in their language.
+    pub strings: locale::Strings,
+    /// Entity templates for `spawn`.  Reloads itself like the theme.
+    pub prefabs: warmy::Res<prefab::Prefabs>,
+    /// Sprite animation clips, for `systems::animate`.
+    pub animations: warmy::Res<animation::AnimationSets>,
     pub input: input::State,
+    /// Raw key presses since the last update tick; cleared after
+    /// every tick whether or not a scene looked at them.
+    pub key_presses: Vec<input::KeyPress>,
     pub specs_world: specs::World,
+    pub game_state: game_state::GameState,
+    pub settings: settings::Settings,
+    /// Scene changes asked for by name; the scene stack carries
+    /// these out after each update.
+    pub scene_requests: Vec<scenes::Request>,
+    /// How to animate the next scene change, if at all.
+    pub next_transition: Option<scenes::Transition>,
+    /// Everything random in the game should come from here, so a
+    /// seed is enough to make a run repeatable.
+    pub rng: StdRng,
+    /// Debug controls for slowing down or stepping game time.
+    pub time: timestep::TimeControl,
 }
 
 impl World {
-    pub fn new(resource_dir: &path::Path) -> Self {
+    pub fn new(
+        ctx: &mut ggez::Context,
+        resource_dir: &path::Path,
+        settings: settings::Settings,
+        seed: u64,
+    ) -> Self {
+        let mut store = Self::store(resource_dir);
+        let theme = ui::theme::load(ctx, &mut store);
+        let strings = locale::Strings::new(ctx, &mut store, &settings.language);
+        let prefabs = resources::load_data(ctx, &mut store, prefab::PREFABS_PATH);
+        let animations = resources::load_data(ctx, &mut store, animation::ANIMATIONS_PATH);
+        Self::with_resources(store, theme, strings, prefabs, animations, settings, seed)
+    }
+
+    /// A world with no window behind it, for running the game
+    /// logic in tests.  There's no theme, so nothing can be drawn,
+    /// and the only resources are the data files: strings, prefabs
+    /// and animations.
+    #[cfg(test)]
+    pub fn headless(resource_dir: &path::Path, settings: settings::Settings, seed: u64) -> Self {
+        let mut store = Self::store(resource_dir);
+        let strings = locale::Strings::headless(resource_dir, &mut store, &settings.language);
+        let prefabs = resources::read_data(resource_dir, prefab::PREFABS_PATH);
+        let animations = resources::read_data(resource_dir, animation::ANIMATIONS_PATH);
+        let theme = ui::theme::ThemeHandle::headless();
+        Self::with_resources(store, theme, strings, prefabs, animations, settings, seed)
+    }
+
+    fn store(resource_dir: &path::Path) -> resources::Store {
         info!("Setting up resource path: {:?}", resource_dir);
-        let opt = warmy::StoreOpt::default().set_root(resource_dir);
-        let store = warmy::Store::new(opt)
-            .expect("Could not create asset store?  Does the directory exist?");
+        resources::Store::new(resource_dir).expect("Could not create asset store?  Does the directory exist?")
+    }
 
+    fn with_resources(
+        store: resources::Store,
+        theme: ui::theme::ThemeHandle,
+        strings: locale::Strings,
+        prefabs: warmy::Res<prefab::Prefabs>,
+        animations: warmy::Res<animation::AnimationSets>,
+        settings: settings::Settings,
+        seed: u64,
+    ) -> Self {
         let mut w = specs::World::new();
+        components::register_components(&mut w);
 
         let mut the_world = Self {
             resources: store,
+            theme,
+            strings,
+            prefabs,
+            animations,
             input: input::State::new(),
+            key_presses: Vec::new(),
             specs_world: w,
+            game_state: game_state::GameState::new(),
+            settings,
+            scene_requests: Vec::new(),
+            next_transition: None,
+            rng: StdRng::seed_from_u64(seed),
+            time: timestep::TimeControl::new(),
         };
 
-        // Make a test entity.
         the_world
-            .specs_world
-            .create_entity()
-            .build();
+            .spawn("player", &prefab::Prefab::at(0.0, 0.0))
+            .expect("There's no player prefab");
 
         the_world
     }
+
+    pub fn request_scene(&mut self, request: scenes::Request) {
+        self.scene_requests.push(request);
+    }
+
+    /// Animates the next scene change, whether it comes from a
+    /// `scenes::Switch` or a request.
+    pub fn transition(&mut self, transition: scenes::Transition) {
+        self.next_transition = Some(transition);
+    }
+
+    /// Makes a new entity from the named prefab, with `overrides`
+    /// replacing whichever of its components they have.  Returns
+    /// `None` if there's no such prefab.
+    pub fn spawn(&mut self, prefab: &str, overrides: &prefab::Prefab) -> Option<specs::Entity> {
+        let components = self.prefabs.borrow().get(prefab)?.with_overrides(overrides);
+        let entity = components.build(self.specs_world.create_entity()).build();
+        debug!("Spawned {:?} as {:?} with {:?}", prefab, entity, components);
+        Some(entity)
+    }
+
+    /// The entity the player controls, if there is one.
+    pub fn player(&self) -> Option<specs::Entity> {
+        let entities = self.specs_world.entities();
+        let players = self.specs_world.read_storage::<components::Player>();
+        (&entities, &players).join().map(|(e, _)| e).next()
+    }
 }